@@ -0,0 +1,53 @@
+use crate::{common::*, enumerate, Error};
+use std::panic;
+
+/// # Enumerates connected USB devices without blocking the async executor
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+///
+/// Runs [`enumerate`] on tokio's blocking thread pool. A panic during
+/// enumeration is resumed on the calling task. Returns an error if the
+/// platform enumeration API fails, or [`Error::Cancelled`] if the runtime is
+/// shutting down.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), usb_enumeration::Error> {
+/// let devices = usb_enumeration::enumerate_async(None, None).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn enumerate_async(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+) -> Result<Vec<UsbDevice>, Error> {
+    match tokio::task::spawn_blocking(move || enumerate(vendor_id, product_id)).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+        Err(_) => Err(Error::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_enumerate_async() {
+        let result = enumerate_async(Some(0xfffd), None).await;
+        assert_eq!(result, enumerate(Some(0xfffd), None));
+    }
+
+    #[test]
+    fn test_enumerate_async_cancelled() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        runtime.shutdown_background();
+
+        let _runtime = handle.enter();
+        let result = futures::executor::block_on(enumerate_async(Some(0xfffd), None));
+        assert_eq!(result, Err(Error::Cancelled));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    channel::{bounded, BoundedSender},
+    observer::Signal,
+    Event, Observer, UsbDevice,
+};
+use std::{fmt, sync::Arc, thread::JoinHandle};
+
+type DeviceCallback = Arc<dyn Fn(&UsbDevice) + Send + Sync>;
+type DevicesCallback = Arc<dyn Fn(&[UsbDevice]) + Send + Sync>;
+type ChangeCallback = Arc<dyn Fn(&UsbDevice, &UsbDevice) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub(crate) struct Callbacks {
+    initial: Option<DevicesCallback>,
+    connect: Option<DeviceCallback>,
+    disconnect: Option<DeviceCallback>,
+    change: Option<ChangeCallback>,
+    reconnect: Option<DeviceCallback>,
+}
+
+impl Callbacks {
+    fn dispatch(&self, event: &Event) {
+        match event {
+            Event::Initial(devices) => {
+                if let Some(callback) = &self.initial {
+                    callback(devices);
+                }
+            }
+            Event::Connect(device) => {
+                if let Some(callback) = &self.connect {
+                    callback(device);
+                }
+            }
+            Event::Disconnect(device) => {
+                if let Some(callback) = &self.disconnect {
+                    callback(device);
+                }
+            }
+            Event::Changed { previous, current } => {
+                if let Some(callback) = &self.change {
+                    callback(previous, current);
+                }
+            }
+            Event::Reconnect(device) => {
+                if let Some(callback) = &self.reconnect {
+                    callback(device);
+                }
+            }
+            Event::Batch {
+                connected,
+                disconnected,
+                changed,
+                reconnected,
+            } => {
+                for device in disconnected {
+                    self.dispatch(&Event::Disconnect(device.clone()));
+                }
+                for device in connected {
+                    self.dispatch(&Event::Connect(device.clone()));
+                }
+                for (previous, current) in changed {
+                    self.dispatch(&Event::Changed {
+                        previous: previous.clone(),
+                        current: current.clone(),
+                    });
+                }
+                for device in reconnected {
+                    self.dispatch(&Event::Reconnect(device.clone()));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Callbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Callbacks")
+    }
+}
+
+/// Keeps the background thread started by [`Observer::run`] running
+///
+/// Dropping this stops the background thread without waiting for it to exit.
+pub struct CallbackGuard {
+    // When this gets dropped, the channel will become disconnected and the
+    // background thread will close
+    _tx_close: BoundedSender<Signal>,
+    _thread: JoinHandle<()>,
+}
+
+impl Observer {
+    /// Called from the background thread with the devices connected when
+    /// [`Observer::run`] is called
+    pub fn on_initial(mut self, callback: impl Fn(&[UsbDevice]) + Send + Sync + 'static) -> Self {
+        self.callbacks.initial = Some(Arc::new(callback));
+        self
+    }
+
+    /// Called from the background thread when a device is connected
+    pub fn on_connect(mut self, callback: impl Fn(&UsbDevice) + Send + Sync + 'static) -> Self {
+        self.callbacks.connect = Some(Arc::new(callback));
+        self
+    }
+
+    /// Called from the background thread when a device is disconnected
+    pub fn on_disconnect(mut self, callback: impl Fn(&UsbDevice) + Send + Sync + 'static) -> Self {
+        self.callbacks.disconnect = Some(Arc::new(callback));
+        self
+    }
+
+    /// Called from the background thread with the previous and current
+    /// details of a device whose details changed, see [`Event::Changed`]
+    pub fn on_change(
+        mut self,
+        callback: impl Fn(&UsbDevice, &UsbDevice) + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.change = Some(Arc::new(callback));
+        self
+    }
+
+    /// Called from the background thread when a device disconnects and
+    /// reconnects within the window set by
+    /// [`Observer::with_reconnect_debounce`]
+    ///
+    /// Neither [`Observer::on_disconnect`] nor [`Observer::on_connect`] is
+    /// called for the reconnect.
+    pub fn on_reconnect(mut self, callback: impl Fn(&UsbDevice) + Send + Sync + 'static) -> Self {
+        self.callbacks.reconnect = Some(Arc::new(callback));
+        self
+    }
+
+    /// Start the background thread and call the registered callbacks for
+    /// device changes
+    ///
+    /// This watches for changes the same way as [`Observer::subscribe`] but
+    /// calls the callbacks instead of sending events on a channel. The
+    /// callbacks are called until the returned guard is dropped.
+    ///
+    /// ```no_run
+    /// use usb_enumeration::Observer;
+    ///
+    /// let _guard = Observer::new()
+    ///     .on_connect(|device| println!("Connected {:?}", device))
+    ///     .on_disconnect(|device| println!("Disconnected {:?}", device))
+    ///     .run();
+    /// ```
+    pub fn run(&self) -> CallbackGuard {
+        let (tx_close, rx_close) = bounded::<Signal>(1);
+
+        let callbacks = self.callbacks.clone();
+        let thread = self.spawn(rx_close, Arc::default(), move |event| {
+            callbacks.dispatch(&event);
+            true
+        });
+
+        CallbackGuard {
+            _tx_close: tx_close,
+            _thread: thread,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_device;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_dispatch() {
+        let connects = Arc::new(AtomicUsize::new(0));
+        let disconnects = Arc::new(AtomicUsize::new(0));
+
+        let observer = Observer::new()
+            .on_connect({
+                let connects = connects.clone();
+                move |_| {
+                    connects.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .on_disconnect({
+                let disconnects = disconnects.clone();
+                move |_| {
+                    disconnects.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+
+        let device = test_device("1", "Device");
+        observer
+            .callbacks
+            .dispatch(&Event::Initial(vec![device.clone()]));
+        observer.callbacks.dispatch(&Event::Connect(device.clone()));
+        observer.callbacks.dispatch(&Event::Connect(device.clone()));
+        observer.callbacks.dispatch(&Event::Disconnect(device));
+
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+        assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_dispatch_changes_and_reconnects() {
+        let changes = Arc::new(AtomicUsize::new(0));
+        let reconnects = Arc::new(AtomicUsize::new(0));
+
+        let observer = Observer::new()
+            .on_change({
+                let changes = changes.clone();
+                move |previous, current| {
+                    assert_eq!(previous.description.as_deref(), Some("Device"));
+                    assert_eq!(current.description.as_deref(), Some("Renamed"));
+                    changes.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .on_reconnect({
+                let reconnects = reconnects.clone();
+                move |_| {
+                    reconnects.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+
+        let previous = test_device("1", "Device");
+        let current = test_device("1", "Renamed");
+        observer.callbacks.dispatch(&Event::Changed {
+            previous: previous.clone(),
+            current: current.clone(),
+        });
+        observer
+            .callbacks
+            .dispatch(&Event::Reconnect(current.clone()));
+        observer.callbacks.dispatch(&Event::Batch {
+            connected: Vec::new(),
+            disconnected: Vec::new(),
+            changed: vec![(previous, current.clone())],
+            reconnected: vec![current],
+        });
+
+        assert_eq!(changes.load(Ordering::SeqCst), 2);
+        assert_eq!(reconnects.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,26 @@
+//! Channels between the background thread and its subscribers
+//!
+//! These are from crossbeam by default. With the `std-channels` feature
+//! enabled, or the `crossbeam` feature disabled, they're from
+//! `std::sync::mpsc` instead.
+
+#[cfg(not(any(feature = "std-channels", not(feature = "crossbeam"))))]
+pub use crossbeam::channel::{
+    bounded, unbounded, Receiver, RecvError, RecvTimeoutError, Sender, Sender as BoundedSender,
+    TryRecvError,
+};
+
+#[cfg(any(feature = "std-channels", not(feature = "crossbeam")))]
+pub use std::sync::mpsc::{
+    Receiver, RecvError, RecvTimeoutError, Sender, SyncSender as BoundedSender, TryRecvError,
+};
+
+#[cfg(any(feature = "std-channels", not(feature = "crossbeam")))]
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    std::sync::mpsc::channel()
+}
+
+#[cfg(any(feature = "std-channels", not(feature = "crossbeam")))]
+pub fn bounded<T>(cap: usize) -> (BoundedSender<T>, Receiver<T>) {
+    std::sync::mpsc::sync_channel(cap)
+}
@@ -1,9 +1,196 @@
-use std::error::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    error::Error,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+/// USB device class codes as defined by the USB-IF
+///
+/// <https://www.usb.org/defined-class-codes>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeviceBaseClass {
+    UseClassCodeFromInterfaceDescriptors,
+    Audio,
+    Communications,
+    HumanInterfaceDevice,
+    Physical,
+    Image,
+    Printer,
+    MassStorage,
+    Hub,
+    CdcData,
+    SmartCard,
+    ContentSecurity,
+    Video,
+    PersonalHealthcare,
+    AudioVideo,
+    Billboard,
+    UsbTypeCBridge,
+    Diagnostic,
+    WirelessController,
+    Miscellaneous,
+    ApplicationSpecific,
+    VendorSpecific,
+    /// Class code not defined by the USB-IF
+    Unknown(u8),
+}
+
+impl From<u8> for DeviceBaseClass {
+    fn from(code: u8) -> Self {
+        match code {
+            0x00 => DeviceBaseClass::UseClassCodeFromInterfaceDescriptors,
+            0x01 => DeviceBaseClass::Audio,
+            0x02 => DeviceBaseClass::Communications,
+            0x03 => DeviceBaseClass::HumanInterfaceDevice,
+            0x05 => DeviceBaseClass::Physical,
+            0x06 => DeviceBaseClass::Image,
+            0x07 => DeviceBaseClass::Printer,
+            0x08 => DeviceBaseClass::MassStorage,
+            0x09 => DeviceBaseClass::Hub,
+            0x0a => DeviceBaseClass::CdcData,
+            0x0b => DeviceBaseClass::SmartCard,
+            0x0d => DeviceBaseClass::ContentSecurity,
+            0x0e => DeviceBaseClass::Video,
+            0x0f => DeviceBaseClass::PersonalHealthcare,
+            0x10 => DeviceBaseClass::AudioVideo,
+            0x11 => DeviceBaseClass::Billboard,
+            0x12 => DeviceBaseClass::UsbTypeCBridge,
+            0xdc => DeviceBaseClass::Diagnostic,
+            0xe0 => DeviceBaseClass::WirelessController,
+            0xef => DeviceBaseClass::Miscellaneous,
+            0xfe => DeviceBaseClass::ApplicationSpecific,
+            0xff => DeviceBaseClass::VendorSpecific,
+            _ => DeviceBaseClass::Unknown(code),
+        }
+    }
+}
+
+impl DeviceBaseClass {
+    /// Human readable name of the class, as listed by the USB-IF
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceBaseClass::UseClassCodeFromInterfaceDescriptors => {
+                "Use Class Code From Interface Descriptors"
+            }
+            DeviceBaseClass::Audio => "Audio",
+            DeviceBaseClass::Communications => "Communications and CDC Control",
+            DeviceBaseClass::HumanInterfaceDevice => "Human Interface Device",
+            DeviceBaseClass::Physical => "Physical",
+            DeviceBaseClass::Image => "Image",
+            DeviceBaseClass::Printer => "Printer",
+            DeviceBaseClass::MassStorage => "Mass Storage",
+            DeviceBaseClass::Hub => "Hub",
+            DeviceBaseClass::CdcData => "CDC Data",
+            DeviceBaseClass::SmartCard => "Smart Card",
+            DeviceBaseClass::ContentSecurity => "Content Security",
+            DeviceBaseClass::Video => "Video",
+            DeviceBaseClass::PersonalHealthcare => "Personal Healthcare",
+            DeviceBaseClass::AudioVideo => "Audio/Video Devices",
+            DeviceBaseClass::Billboard => "Billboard Device",
+            DeviceBaseClass::UsbTypeCBridge => "USB Type-C Bridge",
+            DeviceBaseClass::Diagnostic => "Diagnostic Device",
+            DeviceBaseClass::WirelessController => "Wireless Controller",
+            DeviceBaseClass::Miscellaneous => "Miscellaneous",
+            DeviceBaseClass::ApplicationSpecific => "Application Specific",
+            DeviceBaseClass::VendorSpecific => "Vendor Specific",
+            DeviceBaseClass::Unknown(_) => "Unknown",
+        }
+    }
+
+    /// Numeric class code
+    pub fn code(&self) -> u8 {
+        match self {
+            DeviceBaseClass::UseClassCodeFromInterfaceDescriptors => 0x00,
+            DeviceBaseClass::Audio => 0x01,
+            DeviceBaseClass::Communications => 0x02,
+            DeviceBaseClass::HumanInterfaceDevice => 0x03,
+            DeviceBaseClass::Physical => 0x05,
+            DeviceBaseClass::Image => 0x06,
+            DeviceBaseClass::Printer => 0x07,
+            DeviceBaseClass::MassStorage => 0x08,
+            DeviceBaseClass::Hub => 0x09,
+            DeviceBaseClass::CdcData => 0x0a,
+            DeviceBaseClass::SmartCard => 0x0b,
+            DeviceBaseClass::ContentSecurity => 0x0d,
+            DeviceBaseClass::Video => 0x0e,
+            DeviceBaseClass::PersonalHealthcare => 0x0f,
+            DeviceBaseClass::AudioVideo => 0x10,
+            DeviceBaseClass::Billboard => 0x11,
+            DeviceBaseClass::UsbTypeCBridge => 0x12,
+            DeviceBaseClass::Diagnostic => 0xdc,
+            DeviceBaseClass::WirelessController => 0xe0,
+            DeviceBaseClass::Miscellaneous => 0xef,
+            DeviceBaseClass::ApplicationSpecific => 0xfe,
+            DeviceBaseClass::VendorSpecific => 0xff,
+            DeviceBaseClass::Unknown(code) => *code,
+        }
+    }
+
+    /// Returns `true` for the vendor specific class `0xff` and for unknown
+    /// codes above the range of standard classes, whose meaning is up to
+    /// the vendor
+    pub fn is_vendor_specific(&self) -> bool {
+        match self {
+            DeviceBaseClass::VendorSpecific => true,
+            DeviceBaseClass::Unknown(code) => *code > DeviceBaseClass::UsbTypeCBridge.code(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` for the classes defined by the USB-IF, other than the
+    /// vendor specific class
+    pub fn is_standard(&self) -> bool {
+        !matches!(
+            self,
+            DeviceBaseClass::VendorSpecific | DeviceBaseClass::Unknown(_)
+        )
+    }
+}
+
+impl std::fmt::Display for DeviceBaseClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceBaseClass::Unknown(code) => write!(f, "Unknown (0x{:02x})", code),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// Negotiated USB bus speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UsbSpeed {
+    /// 1.5 Mbit/s
+    Low,
+    /// 12 Mbit/s
+    Full,
+    /// 480 Mbit/s
+    High,
+    /// 5 Gbit/s
+    Super,
+    /// 10 Gbit/s or faster
+    SuperPlus,
+    /// Speed reported by the platform but not recognised
+    Unknown,
+}
 
 /// Discovered USB device
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Devices are equal and hash the same when their `id` matches, regardless of
+/// the other fields. Use [`UsbDevice::eq_all_fields`] to compare every field.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct UsbDevice {
     /// Platform specific unique ID
+    ///
+    /// This is the device's identity for equality and hashing. It isn't
+    /// always stable across reconnects, see [`UsbDevice::stable_id`] for
+    /// what each platform uses.
     pub id: String,
     /// Vendor ID
     pub vendor_id: u16,
@@ -11,20 +198,1040 @@ pub struct UsbDevice {
     pub product_id: u16,
     /// Optional device description
     pub description: Option<String>,
+    /// Optional product name from the USB ID database
+    ///
+    /// This is only set with the `usb-ids` feature enabled.
+    pub product_name: Option<String>,
+    /// Optional manufacturer name
+    pub manufacturer: Option<String>,
+    /// Optional serial number
+    pub serial_number: Option<String>,
+    /// Optional device class (bDeviceClass)
+    pub base_class: Option<DeviceBaseClass>,
+    /// Optional raw device subclass code (bDeviceSubClass), whose meaning
+    /// depends on `base_class`
+    pub sub_class: Option<u8>,
+    /// Optional raw device protocol code (bDeviceProtocol), whose meaning
+    /// depends on `base_class` and `sub_class`
+    pub protocol: Option<u8>,
+    /// Optional negotiated bus speed
+    ///
+    /// This is `None` when the platform doesn't report the speed, which is
+    /// currently always the case on Windows
+    pub speed: Option<UsbSpeed>,
+    /// Optional bus number, as shown by `lsusb`
+    ///
+    /// This can change when the device is reconnected so shouldn't be used
+    /// to identify a device. Currently always `None` on Windows
+    pub bus_number: Option<u8>,
+    /// Optional device address on the bus, as shown by `lsusb`
+    ///
+    /// This is assigned on every connection so shouldn't be used to identify
+    /// a device. Currently always `None` on Windows
+    pub device_address: Option<u8>,
+    /// Optional interface number, for devices that represent a single
+    /// interface of a composite device
+    ///
+    /// On Windows these are the `MI_xx` function instances. This is `None`
+    /// for whole devices and currently always `None` on macOS
+    pub interface_number: Option<u8>,
+    /// Optional device release number (`bcdDevice`) as `(major, minor)`
+    pub device_version: Option<(u8, u8)>,
+    /// Optional USB specification version (`bcdUSB`) as `(major, minor)`
+    ///
+    /// This is currently always `None` on Windows
+    pub usb_version: Option<(u8, u8)>,
+    /// Optional OS path to the device that can be passed to other USB
+    /// libraries
+    ///
+    /// This is the sysfs path on Linux, the IORegistry service plane path on
+    /// macOS and the device instance path on Windows.
+    pub device_path: Option<String>,
+    /// Optional maximum current the device draws from the bus in mA
+    ///
+    /// This is the active configuration's `bMaxPower`, which is in units of
+    /// 2mA, scaled to mA. It is currently always `None` on Windows.
+    pub max_power_ma: Option<u16>,
+    /// Optional flag for whether the active configuration reports the device
+    /// as self-powered
+    ///
+    /// This is read from bit 6 of the configuration's `bmAttributes` and is
+    /// currently only available on Linux.
+    pub self_powered: Option<bool>,
+    /// Optional name of the driver bound to the device
+    ///
+    /// This is `None` when no driver is bound. It is the kernel driver on
+    /// Linux, the service name on Windows and the IOKit class of the first
+    /// client of the device on macOS, which for composite devices is usually
+    /// an interface rather than a driver.
+    pub driver: Option<String>,
+    /// Optional `id` of the upstream device, such as the hub the device is
+    /// connected to or the composite device an interface belongs to
+    ///
+    /// This is `None` for root hubs. See [`build_tree`](crate::build_tree).
+    pub parent_id: Option<String>,
+    /// Optional number of the port on the parent hub the device is connected
+    /// to
+    ///
+    /// Together with the parent this identifies a physical port, so it stays
+    /// the same when the same device is reconnected to the same port.
+    pub port_number: Option<u8>,
+    /// Optional number of configurations from `bNumConfigurations`
+    ///
+    /// This is `None` on Windows, which doesn't expose the device descriptor
+    /// without opening the hub.
+    pub num_configurations: Option<u8>,
+    /// Interfaces of the device's active configuration, ordered by number
+    ///
+    /// On Windows only composite devices report their interfaces and with
+    /// WebUSB only opened devices do. This is empty for interfaces themselves
+    /// and when the interfaces can't be read.
+    pub interfaces: Vec<UsbInterface>,
+    /// Optional macOS `locationID`, which encodes the bus in the top byte and
+    /// the port at each hub level in the nibbles below it
+    ///
+    /// This stays the same when the device is reconnected to the same port,
+    /// so it's part of the macOS `id`. This is `None` on other platforms,
+    /// which report the port in `port_number`.
+    pub location_id: Option<u32>,
+    /// Optional flag for whether the device can be unplugged, as opposed to
+    /// being built in
+    ///
+    /// Root hubs and internal devices like laptop webcams usually report
+    /// `Some(false)`. This is `None` where the platform can't tell.
+    pub removable: Option<bool>,
+    /// Whether the device is connected
+    ///
+    /// This is only `false` on Windows for devices returned by
+    /// [`enumerate_including_non_present`](crate::enumerate_including_non_present),
+    /// which Windows remembers from a previous connection.
+    pub present: bool,
+    /// The Windows hardware IDs of the device, most specific first
+    ///
+    /// These are the raw `SPDRP_HARDWAREID` strings, like
+    /// `USB\VID_046D&PID_C52B&REV_1203`. This is empty on other platforms.
+    pub hardware_ids: Vec<String>,
+    /// Every property the platform reports for the device, with the values
+    /// as strings
+    ///
+    /// This is only filled in with the `raw-properties` feature, since it
+    /// allocates for every device. The keys are the udev property names on
+    /// Linux, the IORegistry keys on macOS, the `SPDRP_*` names on Windows
+    /// and the descriptor field names on FreeBSD. Windows lists are joined
+    /// with `,`. This is always `None` with WebUSB.
+    pub raw_properties: Option<HashMap<String, String>>,
+    /// The `bConfigurationValue` of the active configuration, or `0` if the
+    /// device isn't configured
+    ///
+    /// This is always `None` on Windows, where only the hub the device is
+    /// connected to knows it, see [`UsbDevice::read_active_configuration`].
+    /// It is also always `None` with WebUSB.
+    pub active_configuration: Option<u8>,
+}
+
+/// An interface of a [`UsbDevice`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UsbInterface {
+    /// Interface number from `bInterfaceNumber`
+    pub number: u8,
+    /// Interface class from `bInterfaceClass`
+    pub base_class: DeviceBaseClass,
+    /// Interface subclass from `bInterfaceSubClass`
+    pub sub_class: u8,
+    /// Interface protocol from `bInterfaceProtocol`
+    pub protocol: u8,
+    /// Optional interface description
+    pub description: Option<String>,
+}
+
+impl UsbDevice {
+    /// Vendor and Product ID of the device
+    pub fn vid_pid(&self) -> VidPid {
+        VidPid::new(self.vendor_id, self.product_id)
+    }
+
+    /// Returns `true` if the device has the given IDs, where `None` matches
+    /// any ID
+    ///
+    /// This is the filter used by [`enumerate`](crate::enumerate).
+    pub fn matches(&self, vendor_id: Option<u16>, product_id: Option<u16>) -> bool {
+        vendor_id.is_none_or(|id| id == self.vendor_id)
+            && product_id.is_none_or(|id| id == self.product_id)
+    }
+
+    /// Returns `true` if the device has the given Vendor and Product ID
+    pub fn matches_vid_pid(&self, vid_pid: VidPid) -> bool {
+        self.vid_pid() == vid_pid
+    }
+
+    /// An identifier for the device that is stable across reconnects and
+    /// application restarts where possible
+    ///
+    /// When the device has a serial number this is `vid:pid:serial`, with the
+    /// IDs as lowercase hex, which is the same on every platform and survives
+    /// replugging into a different port. Otherwise this falls back to the
+    /// platform `id`, which is:
+    ///
+    /// - Windows: the device instance path, stable across restarts and
+    ///   reconnects to the same port
+    /// - Linux: the sysfs `DEVPATH` without its `/devices/` prefix, stable
+    ///   across restarts while the device stays on the same port. It includes
+    ///   the bus and port numbers so it changes if the device moves port or
+    ///   the kernel numbers the buses differently
+    /// - macOS: the `locationID` and the vendor and product IDs as
+    ///   `location:vid:pid` in lowercase hex, stable across reconnects to the
+    ///   same port. Devices without a `locationID` use the IOKit `sessionID`,
+    ///   which changes every time the device is connected
+    ///
+    /// Note that some devices report non-unique serial numbers.
+    pub fn stable_id(&self) -> String {
+        match &self.serial_number {
+            Some(serial_number) => format!("{}:{}", self.vid_pid(), serial_number),
+            None => self.id.clone(),
+        }
+    }
+
+    /// Returns `true` if the device or any of its interfaces reports the
+    /// given base class
+    ///
+    /// Composite devices report
+    /// [`DeviceBaseClass::UseClassCodeFromInterfaceDescriptors`] as their
+    /// device-level class, so only their
+    /// [`interfaces`](UsbDevice::interfaces) match. Where the backend can't
+    /// read the interfaces only the device-level class is checked.
+    pub fn has_class(&self, class: DeviceBaseClass) -> bool {
+        self.base_class == Some(class)
+            || self
+                .interfaces
+                .iter()
+                .any(|interface| interface.base_class == class)
+    }
+
+    /// Returns `true` if the device reports the hub class
+    ///
+    /// See [`UsbDevice::has_class`] for the limitations.
+    pub fn is_hub(&self) -> bool {
+        self.has_class(DeviceBaseClass::Hub)
+    }
+
+    /// Returns `true` if the device reports the mass storage class
+    ///
+    /// See [`UsbDevice::has_class`] for the limitations.
+    pub fn is_mass_storage(&self) -> bool {
+        self.has_class(DeviceBaseClass::MassStorage)
+    }
+
+    /// Returns `true` if the device has several functions described by its
+    /// interfaces
+    ///
+    /// This is the case when the device-level class is
+    /// [`DeviceBaseClass::UseClassCodeFromInterfaceDescriptors`], or the
+    /// miscellaneous class with subclass `0x02` and protocol `0x01` used by
+    /// devices with Interface Association Descriptors. Windows devices,
+    /// which have [`hardware_ids`](UsbDevice::hardware_ids) and only report
+    /// interfaces for composite devices, also count if they have any
+    /// [`interfaces`](UsbDevice::interfaces). Interfaces themselves are never
+    /// composite.
+    pub fn is_composite(&self) -> bool {
+        if self.interface_number.is_some() {
+            return false;
+        }
+
+        match self.base_class {
+            Some(DeviceBaseClass::UseClassCodeFromInterfaceDescriptors) => true,
+            Some(DeviceBaseClass::Miscellaneous)
+                if self.sub_class == Some(0x02) && self.protocol == Some(0x01) =>
+            {
+                true
+            }
+            _ => !self.hardware_ids.is_empty() && !self.interfaces.is_empty(),
+        }
+    }
+
+    /// Reads the `bConfigurationValue` of the active configuration
+    ///
+    /// On Windows this asks the hub the device is connected to, which is too
+    /// slow to do for every device on every enumeration, so it is `None` for
+    /// interfaces, root hubs and devices that aren't connected. Elsewhere
+    /// this is [`active_configuration`](UsbDevice::active_configuration).
+    pub fn read_active_configuration(&self) -> Option<u8> {
+        #[cfg(target_os = "windows")]
+        {
+            crate::windows::read_active_configuration(self)
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            self.active_configuration
+        }
+    }
+
+    /// Whether the device draws its power from the bus, if known
+    pub fn is_bus_powered(&self) -> Option<bool> {
+        self.self_powered.map(|self_powered| !self_powered)
+    }
+
+    /// The device release number formatted like `2.14`
+    pub fn device_version_string(&self) -> Option<String> {
+        self.device_version
+            .map(|(major, minor)| format!("{}.{:02}", major, minor))
+    }
+
+    /// Returns `true` if every field of both devices is equal, rather than
+    /// just the `id`
+    pub fn eq_all_fields(&self, other: &UsbDevice) -> bool {
+        let UsbDevice {
+            id,
+            vendor_id,
+            product_id,
+            description,
+            manufacturer,
+            serial_number,
+            base_class,
+            speed,
+            bus_number,
+            device_address,
+            interface_number,
+            device_version,
+            usb_version,
+            device_path,
+            max_power_ma,
+            self_powered,
+            driver,
+            parent_id,
+            port_number,
+            sub_class,
+            protocol,
+            product_name,
+            num_configurations,
+            interfaces,
+            location_id,
+            removable,
+            present,
+            hardware_ids,
+            raw_properties,
+            active_configuration,
+        } = self;
+
+        *id == other.id
+            && *vendor_id == other.vendor_id
+            && *product_id == other.product_id
+            && *description == other.description
+            && *manufacturer == other.manufacturer
+            && *serial_number == other.serial_number
+            && *base_class == other.base_class
+            && *speed == other.speed
+            && *bus_number == other.bus_number
+            && *device_address == other.device_address
+            && *interface_number == other.interface_number
+            && *device_version == other.device_version
+            && *usb_version == other.usb_version
+            && *device_path == other.device_path
+            && *max_power_ma == other.max_power_ma
+            && *self_powered == other.self_powered
+            && *driver == other.driver
+            && *parent_id == other.parent_id
+            && *port_number == other.port_number
+            && *sub_class == other.sub_class
+            && *protocol == other.protocol
+            && *product_name == other.product_name
+            && *num_configurations == other.num_configurations
+            && *interfaces == other.interfaces
+            && *location_id == other.location_id
+            && *removable == other.removable
+            && *present == other.present
+            && *hardware_ids == other.hardware_ids
+            && *raw_properties == other.raw_properties
+            && *active_configuration == other.active_configuration
+    }
+}
+
+impl PartialEq for UsbDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct ParseError;
+impl Eq for UsbDevice {}
 
-impl std::fmt::Display for ParseError {
+impl Hash for UsbDevice {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// Formats a short summary like `046d:c52b Logitech USB Receiver (serial: ABC123)`
+///
+/// The manufacturer and description are included when present, falling back
+/// to the base class name. Use `{:?}` for every field.
+impl std::fmt::Display for UsbDevice {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Parse Error")?;
+        write!(f, "{}", self.vid_pid())?;
+
+        let description = self.description.as_ref().or(self.product_name.as_ref());
+
+        if let Some(manufacturer) = &self.manufacturer {
+            // Descriptions often already start with the manufacturer
+            if !description.is_some_and(|d| d.starts_with(manufacturer.as_str())) {
+                write!(f, " {}", manufacturer)?;
+            }
+        }
+
+        match (description, self.base_class) {
+            (Some(description), _) => write!(f, " {}", description)?,
+            (None, Some(class)) if self.manufacturer.is_none() => write!(f, " {}", class)?,
+            _ => {}
+        }
+
+        if let Some(serial_number) = &self.serial_number {
+            write!(f, " (serial: {})", serial_number)?;
+        }
+
         Ok(())
     }
 }
 
-impl Error for ParseError {
+/// Devices are ordered by `vendor_id`, then `product_id`, then `id`
+impl PartialOrd for UsbDevice {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UsbDevice {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.vendor_id, self.product_id, &self.id).cmp(&(
+            other.vendor_id,
+            other.product_id,
+            &other.id,
+        ))
+    }
+}
+
+/// USB Vendor and Product ID pair
+///
+/// Parses from and displays as the `lsusb` form, e.g. `046d:c52b`
+///
+/// ```
+/// use usb_enumeration::VidPid;
+///
+/// let vid_pid: VidPid = "046d:c52b".parse()?;
+/// assert_eq!(vid_pid.vendor_id, 0x046d);
+/// assert_eq!(vid_pid.product_id, 0xc52b);
+/// # Ok::<(), usb_enumeration::VidPidParseError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VidPid {
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+}
+
+impl VidPid {
+    pub fn new(vendor_id: u16, product_id: u16) -> Self {
+        VidPid {
+            vendor_id,
+            product_id,
+        }
+    }
+}
+
+impl std::fmt::Display for VidPid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)
+    }
+}
+
+impl FromStr for VidPid {
+    type Err = VidPidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (vendor_id, product_id) = s
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| VidPidParseError::MissingSeparator(s.to_string()))?;
+
+        Ok(VidPid {
+            vendor_id: parse_hex_id(vendor_id)
+                .ok_or_else(|| VidPidParseError::InvalidVendorId(vendor_id.to_string()))?,
+            product_id: parse_hex_id(product_id)
+                .ok_or_else(|| VidPidParseError::InvalidProductId(product_id.to_string()))?,
+        })
+    }
+}
+
+/// Replaces control characters in a description read from the OS with
+/// spaces, collapses runs of whitespace and trims it, returning `None` if
+/// nothing is left
+pub(crate) fn clean_description(description: &str) -> Option<String> {
+    let description = description
+        .split(|c: char| c.is_control() || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if description.is_empty() {
+        None
+    } else {
+        Some(description)
+    }
+}
+
+/// Decodes a binary-coded decimal version like `0x0214` into `(2, 14)`
+pub(crate) fn version_from_bcd(bcd: u16) -> (u8, u8) {
+    let decode = |byte: u8| (byte >> 4) * 10 + (byte & 0x0f);
+    (decode((bcd >> 8) as u8), decode(bcd as u8))
+}
+
+/// Parses up to 4 hex digits with an optional `0x` prefix
+fn parse_hex_id(id: &str) -> Option<u16> {
+    let id = id
+        .strip_prefix("0x")
+        .or_else(|| id.strip_prefix("0X"))
+        .unwrap_or(id);
+
+    if id.is_empty() || id.len() > 4 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u16::from_str_radix(id, 16).ok()
+}
+
+/// Parses the vendor and product ID from a Windows hardware ID
+///
+/// Handles IDs like `USB\VID_046D&PID_C52B` and
+/// `USB\VID_046D&PID_C52B&MI_01` in any case, returning `None` if either ID
+/// is missing or isn't 4 hex digits.
+///
+/// ```
+/// use usb_enumeration::{parse_vid_pid, VidPid};
+///
+/// assert_eq!(
+///     parse_vid_pid("USB\\VID_046D&PID_C52B&MI_01"),
+///     Some(VidPid::new(0x046d, 0xc52b))
+/// );
+/// assert_eq!(parse_vid_pid("ROOT\\DEVICE"), None);
+/// ```
+pub fn parse_vid_pid(hardware_id: &str) -> Option<VidPid> {
+    let id = hardware_id.to_ascii_uppercase();
+
+    let field = |name: &str| {
+        let start = id.find(name)? + name.len();
+        let digits = id.get(start..start + 4)?;
+
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u16::from_str_radix(digits, 16).ok()
+    };
+
+    Some(VidPid::new(field("VID_")?, field("PID_")?))
+}
+
+/// Error returned when parsing a [`VidPid`] fails
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VidPidParseError {
+    /// The input has no `:` between the vendor and product ID
+    MissingSeparator(String),
+    /// The vendor ID is not 1 to 4 hex digits
+    InvalidVendorId(String),
+    /// The product ID is not 1 to 4 hex digits
+    InvalidProductId(String),
+}
+
+impl std::fmt::Display for VidPidParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VidPidParseError::MissingSeparator(s) => write!(
+                f,
+                "Expected vendor and product ID separated by ':' (e.g. 046d:c52b) but found '{}'",
+                s
+            )?,
+            VidPidParseError::InvalidVendorId(s) => {
+                write!(f, "Invalid vendor ID '{}', expected up to 4 hex digits", s)?
+            }
+            VidPidParseError::InvalidProductId(s) => {
+                write!(f, "Invalid product ID '{}', expected up to 4 hex digits", s)?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for VidPidParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Error returned when the platform enumeration API fails
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EnumerationError {
+    /// Platform specific error code
+    pub code: i64,
+    /// Name of the platform call that failed
+    pub call: &'static str,
+}
+
+impl EnumerationError {
+    pub(crate) fn new(call: &'static str, code: impl Into<i64>) -> Self {
+        EnumerationError {
+            code: code.into(),
+            call,
+        }
+    }
+}
+
+impl std::fmt::Display for EnumerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed with error code {}", self.call, self.code)?;
+        Ok(())
+    }
+}
+
+impl Error for EnumerationError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         None
     }
 }
+
+/// Details of a device that was found but couldn't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceError {
+    /// Platform id or path of the device
+    pub id: String,
+    /// The property or platform call that failed
+    pub step: &'static str,
+    /// Why the step failed
+    pub message: String,
+}
+
+impl DeviceError {
+    pub(crate) fn new(id: impl Into<String>, step: &'static str, message: impl ToString) -> Self {
+        DeviceError {
+            id: id.into(),
+            step,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Could not read {} for device {}: {}",
+            self.step, self.id, self.message
+        )
+    }
+}
+
+impl Error for DeviceError {}
+
+/// Error returned by [`check_permissions`](crate::check_permissions) when
+/// the process can't fully read USB devices
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PermissionError {
+    /// The platform enumeration API failed
+    Enumeration(EnumerationError),
+    /// USB devices were found but none had their vendor and product IDs,
+    /// which on Linux usually means udev isn't running or its database can't
+    /// be read
+    MissingProperties {
+        /// Number of devices without IDs
+        devices: usize,
+    },
+    /// The device nodes of these devices can't be opened for reading and
+    /// writing, which usually means no udev rule grants access to them, for
+    /// example with `TAG+="uaccess"`
+    DeviceNodes(Vec<String>),
+}
+
+impl std::fmt::Display for PermissionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionError::Enumeration(e) => write!(f, "Could not enumerate devices: {}", e),
+            PermissionError::MissingProperties { devices } => write!(
+                f,
+                "None of the {} USB devices have vendor and product IDs, check that udev is running",
+                devices
+            ),
+            PermissionError::DeviceNodes(nodes) => write!(
+                f,
+                "Permission denied opening {}, check the udev rules for these devices",
+                nodes.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for PermissionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PermissionError::Enumeration(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Builds a device with the given id and description, for tests
+#[cfg(test)]
+pub(crate) fn test_device(id: &str, description: &str) -> UsbDevice {
+    UsbDevice {
+        id: id.to_string(),
+        vendor_id: 0x1234,
+        product_id: 0x5678,
+        description: Some(description.to_string()),
+        manufacturer: None,
+        serial_number: None,
+        base_class: None,
+        speed: None,
+        bus_number: None,
+        device_address: None,
+        interface_number: None,
+        device_version: None,
+        usb_version: None,
+        device_path: None,
+        max_power_ma: None,
+        self_powered: None,
+        driver: None,
+        parent_id: None,
+        port_number: None,
+        sub_class: None,
+        protocol: None,
+        product_name: None,
+        num_configurations: None,
+        interfaces: Vec::new(),
+        location_id: None,
+        removable: None,
+        present: true,
+        hardware_ids: Vec::new(),
+        raw_properties: None,
+        active_configuration: None,
+    }
+}
+
+/// Builds an enumeration source for [`Observer::with_source`](crate::Observer::with_source)
+/// that returns each device list in turn, then keeps returning the last one
+#[cfg(all(test, feature = "observer"))]
+pub(crate) fn scripted_source(
+    script: Vec<Vec<UsbDevice>>,
+) -> impl Fn(Option<u16>, Option<u16>) -> Result<Vec<UsbDevice>, crate::Error> + Send + Sync + 'static
+{
+    let script = std::sync::Mutex::new(script);
+    move |_, _| {
+        let mut script = script.lock().unwrap();
+        if script.len() > 1 {
+            Ok(script.remove(0))
+        } else {
+            Ok(script[0].clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let device = test_device("1", "Device");
+
+        assert!(device.matches(None, None));
+        assert!(device.matches(Some(0x1234), None));
+        assert!(device.matches(None, Some(0x5678)));
+        assert!(device.matches(Some(0x1234), Some(0x5678)));
+        assert!(!device.matches(Some(0x1234), Some(0x0001)));
+        assert!(!device.matches(Some(0x0001), None));
+
+        assert!(device.matches_vid_pid(VidPid::new(0x1234, 0x5678)));
+        assert!(!device.matches_vid_pid(VidPid::new(0x1234, 0x0001)));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut device = test_device("1", "USB Receiver");
+        assert_eq!(device.to_string(), "1234:5678 USB Receiver");
+
+        device.manufacturer = Some("Logitech".to_string());
+        device.serial_number = Some("ABC123".to_string());
+        assert_eq!(
+            device.to_string(),
+            "1234:5678 Logitech USB Receiver (serial: ABC123)"
+        );
+
+        device.description = Some("Logitech USB Receiver".to_string());
+        assert_eq!(
+            device.to_string(),
+            "1234:5678 Logitech USB Receiver (serial: ABC123)"
+        );
+
+        let mut device = test_device("1", "");
+        device.description = None;
+        assert_eq!(device.to_string(), "1234:5678");
+
+        device.base_class = Some(DeviceBaseClass::Hub);
+        assert_eq!(device.to_string(), "1234:5678 Hub");
+    }
+
+    #[test]
+    fn test_device_identity() {
+        let a = test_device("1", "Device");
+        let b = test_device("1", "Renamed Device");
+        let c = test_device("2", "Device");
+
+        assert_eq!(a, b);
+        assert!(!a.eq_all_fields(&b));
+        assert!(a.eq_all_fields(&a.clone()));
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_stable_id() {
+        let mut device = test_device("platform-id", "Device");
+        assert_eq!(device.stable_id(), "platform-id");
+
+        device.serial_number = Some("ABC123".to_string());
+        assert_eq!(device.stable_id(), "1234:5678:ABC123");
+    }
+
+    #[test]
+    fn test_version_from_bcd() {
+        assert_eq!(version_from_bcd(0x0214), (2, 14));
+        assert_eq!(version_from_bcd(0x0200), (2, 0));
+        assert_eq!(version_from_bcd(0x1099), (10, 99));
+
+        let mut device = test_device("1", "Device");
+        assert_eq!(device.device_version_string(), None);
+        device.device_version = Some((2, 4));
+        assert_eq!(device.device_version_string().as_deref(), Some("2.04"));
+    }
+
+    #[test]
+    fn test_device_error_display() {
+        let error = DeviceError::new("/devices/usb1", "ID_VENDOR_ID", "invalid digit");
+        assert_eq!(
+            error.to_string(),
+            "Could not read ID_VENDOR_ID for device /devices/usb1: invalid digit"
+        );
+    }
+
+    #[test]
+    fn test_device_ordering() {
+        let mut a = test_device("2", "Device");
+        a.vendor_id = 0x0001;
+        let b = test_device("1", "Device");
+        let c = test_device("3", "Device");
+
+        let mut devices = [c, b, a];
+        devices.sort();
+        assert!(devices.iter().map(|d| &d.id).eq(["2", "1", "3"].iter()));
+    }
+
+    #[test]
+    fn test_class_helpers() {
+        let mut device = test_device("1", "Device");
+        assert!(!device.is_hub());
+        assert!(!device.has_class(DeviceBaseClass::Hub));
+
+        device.base_class = Some(DeviceBaseClass::Hub);
+        assert!(device.is_hub());
+        assert!(!device.is_mass_storage());
+        assert!(device.has_class(DeviceBaseClass::Hub));
+
+        // Composite devices match on their interface classes
+        device.base_class = Some(DeviceBaseClass::UseClassCodeFromInterfaceDescriptors);
+        device.interfaces = vec![UsbInterface {
+            number: 0,
+            base_class: DeviceBaseClass::MassStorage,
+            sub_class: 0x06,
+            protocol: 0x50,
+            description: None,
+        }];
+        assert!(device.is_mass_storage());
+        assert!(!device.is_hub());
+    }
+
+    #[test]
+    fn test_is_composite() {
+        let mut device = test_device("1", "Device");
+        assert!(!device.is_composite());
+
+        device.base_class = Some(DeviceBaseClass::UseClassCodeFromInterfaceDescriptors);
+        assert!(device.is_composite());
+
+        device.base_class = Some(DeviceBaseClass::Miscellaneous);
+        assert!(!device.is_composite());
+        device.sub_class = Some(0x02);
+        device.protocol = Some(0x01);
+        assert!(device.is_composite());
+
+        // The interfaces of a composite device aren't composite themselves
+        device.interface_number = Some(0);
+        assert!(!device.is_composite());
+    }
+
+    #[test]
+    fn test_is_composite_windows() {
+        let mut device = test_device("1", "Device");
+        device.interfaces = vec![UsbInterface {
+            number: 0,
+            base_class: DeviceBaseClass::HumanInterfaceDevice,
+            sub_class: 0x01,
+            protocol: 0x01,
+            description: None,
+        }];
+
+        // Other platforms report the interfaces of every device
+        assert!(!device.is_composite());
+
+        device.hardware_ids = vec!["USB\\VID_1234&PID_5678".to_string()];
+        assert!(device.is_composite());
+    }
+
+    #[test]
+    fn test_is_bus_powered() {
+        let mut device = test_device("1", "Device");
+        assert_eq!(device.is_bus_powered(), None);
+        device.self_powered = Some(true);
+        assert_eq!(device.is_bus_powered(), Some(false));
+    }
+
+    #[test]
+    fn test_vid_pid_parse() {
+        let expected = VidPid::new(0x046d, 0xc52b);
+
+        assert_eq!("046d:c52b".parse(), Ok(expected));
+        assert_eq!("046D:C52B".parse(), Ok(expected));
+        assert_eq!("0x046d:0xc52b".parse(), Ok(expected));
+        assert_eq!("46d:c52b".parse(), Ok(expected));
+        assert_eq!(expected.to_string(), "046d:c52b");
+    }
+
+    #[test]
+    fn test_vid_pid_parse_errors() {
+        assert_eq!(
+            "046d".parse::<VidPid>(),
+            Err(VidPidParseError::MissingSeparator("046d".to_string()))
+        );
+        assert_eq!(
+            "04xd:c52b".parse::<VidPid>(),
+            Err(VidPidParseError::InvalidVendorId("04xd".to_string()))
+        );
+        assert_eq!(
+            "046d:".parse::<VidPid>(),
+            Err(VidPidParseError::InvalidProductId("".to_string()))
+        );
+        assert_eq!(
+            "046d:c52b1".parse::<VidPid>(),
+            Err(VidPidParseError::InvalidProductId("c52b1".to_string()))
+        );
+        assert_eq!(
+            "046d:+c52".parse::<VidPid>(),
+            Err(VidPidParseError::InvalidProductId("+c52".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_base_class_code() {
+        for code in 0..=u8::MAX {
+            assert_eq!(DeviceBaseClass::from(code).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_base_class_kind() {
+        assert!(DeviceBaseClass::Hub.is_standard());
+        assert!(!DeviceBaseClass::Hub.is_vendor_specific());
+        assert!(DeviceBaseClass::UseClassCodeFromInterfaceDescriptors.is_standard());
+
+        assert!(DeviceBaseClass::VendorSpecific.is_vendor_specific());
+        assert!(!DeviceBaseClass::VendorSpecific.is_standard());
+
+        assert!(DeviceBaseClass::Unknown(0x42).is_vendor_specific());
+        assert!(!DeviceBaseClass::Unknown(0x42).is_standard());
+
+        // Gaps within the standard range are reserved rather than vendor
+        // specific
+        assert!(!DeviceBaseClass::Unknown(0x04).is_vendor_specific());
+        assert!(!DeviceBaseClass::Unknown(0x04).is_standard());
+    }
+
+    #[test]
+    fn test_base_class_display() {
+        assert_eq!(
+            DeviceBaseClass::HumanInterfaceDevice.to_string(),
+            "Human Interface Device"
+        );
+        assert_eq!(DeviceBaseClass::Unknown(0x42).name(), "Unknown");
+        assert_eq!(DeviceBaseClass::Unknown(0x42).to_string(), "Unknown (0x42)");
+    }
+
+    #[test]
+    fn test_base_class_from_code() {
+        assert_eq!(DeviceBaseClass::from(0x09), DeviceBaseClass::Hub);
+        assert_eq!(DeviceBaseClass::from(0xff), DeviceBaseClass::VendorSpecific);
+        assert_eq!(DeviceBaseClass::from(0x04), DeviceBaseClass::Unknown(0x04));
+        assert_eq!(DeviceBaseClass::from(0x42), DeviceBaseClass::Unknown(0x42));
+    }
+
+    #[test]
+    fn test_parse_vid_pid() {
+        assert_eq!(
+            parse_vid_pid("USB\\VID_046D&PID_C52B"),
+            Some(VidPid::new(0x046d, 0xc52b))
+        );
+        assert_eq!(
+            parse_vid_pid("USB\\VID_046D&PID_C52B&MI_01\\7&1234&0&0001"),
+            Some(VidPid::new(0x046d, 0xc52b))
+        );
+        assert_eq!(
+            parse_vid_pid("usb\\vid_046d&pid_c52b&rev_1201"),
+            Some(VidPid::new(0x046d, 0xc52b))
+        );
+    }
+
+    #[test]
+    fn test_parse_vid_pid_malformed() {
+        assert_eq!(parse_vid_pid(""), None);
+        assert_eq!(parse_vid_pid("ROOT\\DEVICE"), None);
+        assert_eq!(parse_vid_pid("USB\\VID_046D"), None);
+        assert_eq!(parse_vid_pid("USB\\VID_046&PID_C52B"), None);
+        assert_eq!(parse_vid_pid("USB\\VID_+46D&PID_C52B"), None);
+        assert_eq!(parse_vid_pid("USB\\VID_ZZZZ&PID_C52B"), None);
+        assert_eq!(parse_vid_pid("USB\\VID_046D&PID_C5"), None);
+    }
+
+    #[test]
+    fn test_clean_description() {
+        assert_eq!(
+            clean_description(" USB Receiver\u{7}  \r\n").as_deref(),
+            Some("USB Receiver")
+        );
+        assert_eq!(
+            clean_description("USB\tReceiver").as_deref(),
+            Some("USB Receiver")
+        );
+        assert_eq!(
+            clean_description("USB \u{7}\r\n  Receiver").as_deref(),
+            Some("USB Receiver")
+        );
+        assert_eq!(clean_description(" \u{1b} "), None);
+        assert_eq!(clean_description(""), None);
+    }
+
+    #[test]
+    fn test_permission_error_display() {
+        let error = PermissionError::DeviceNodes(vec![
+            "/dev/bus/usb/001/004".to_string(),
+            "/dev/bus/usb/002/002".to_string(),
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "Permission denied opening /dev/bus/usb/001/004, /dev/bus/usb/002/002, check the udev rules for these devices"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    channel::{
+        bounded, unbounded, BoundedSender, Receiver, RecvError, RecvTimeoutError, Sender,
+        TryRecvError,
+    },
+    observer::Signal,
+    stats::ObserverStats,
+    Event, Observer, UsbDevice,
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Watches for device changes on one background thread and shares them with
+/// any number of filtered [`SourceSubscription`]s
+///
+/// Each [`Observer`] enumerates every device on its own thread, so many
+/// Observers watching different devices scan the bus many times per poll.
+/// An `EnumerationSource` scans once per poll and sends each subscription
+/// the events for the devices it is interested in.
+///
+/// The background thread stops when the source is dropped, which also ends
+/// its subscriptions.
+///
+/// ```no_run
+/// use usb_enumeration::EnumerationSource;
+///
+/// let source = EnumerationSource::new();
+/// let keyboards = source.subscribe(Some(0x046d), None);
+/// let scopes = source.subscribe(Some(0x0ce9), None);
+///
+/// for event in keyboards.rx_event.iter() {
+///     println!("{:?}", event);
+/// }
+/// ```
+pub struct EnumerationSource {
+    shared: Arc<Mutex<Shared>>,
+    stats: Arc<ObserverStats>,
+    // When this gets dropped, the channel will become disconnected and the
+    // background thread will close
+    tx_close: BoundedSender<Signal>,
+    _thread: JoinHandle<()>,
+}
+
+/// The devices seen by the background thread and who to send them to
+#[derive(Default)]
+struct Shared {
+    /// `None` until the initial enumeration has finished
+    devices: Option<Vec<UsbDevice>>,
+    subscribers: Vec<Subscriber>,
+}
+
+struct Subscriber {
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    tx_event: Sender<Event>,
+}
+
+impl Default for EnumerationSource {
+    fn default() -> Self {
+        EnumerationSource::new()
+    }
+}
+
+impl EnumerationSource {
+    /// Start watching every device with the default [`Observer`] settings
+    pub fn new() -> Self {
+        EnumerationSource::from_observer(Observer::new())
+    }
+
+    /// Start watching the devices matched by `observer`, using its poll
+    /// interval and other settings
+    ///
+    /// Every subscription is sent an `Initial` event when it subscribes, so
+    /// [`Observer::with_initial`] and [`Observer::with_initial_as_connects`]
+    /// are ignored.
+    pub fn from_observer(observer: Observer) -> Self {
+        let (tx_close, rx_close) = bounded::<Signal>(1);
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let stats = Arc::new(ObserverStats::default());
+
+        let thread = observer
+            .with_initial(true)
+            .with_initial_as_connects(false)
+            .spawn(rx_close, stats.clone(), {
+                let shared = shared.clone();
+                move |event| {
+                    lock(&shared).send(event);
+                    true
+                }
+            });
+
+        EnumerationSource {
+            shared,
+            stats,
+            tx_close,
+            _thread: thread,
+        }
+    }
+
+    /// Subscribe to the events for devices with the given vendor and
+    /// product IDs
+    ///
+    /// The subscription starts with an `Initial` event listing the matching
+    /// devices, as soon as the first enumeration has finished. Dropping the
+    /// subscription stops its events without affecting the other
+    /// subscriptions.
+    pub fn subscribe(&self, vendor_id: Option<u16>, product_id: Option<u16>) -> SourceSubscription {
+        let (tx_event, rx_event) = unbounded();
+
+        let subscriber = Subscriber {
+            vendor_id,
+            product_id,
+            tx_event,
+        };
+
+        let mut shared = lock(&self.shared);
+
+        if let Some(devices) = &shared.devices {
+            subscriber.send(&Event::Initial(devices.clone()));
+        }
+
+        shared.subscribers.push(subscriber);
+
+        SourceSubscription {
+            rx_event,
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Re-enumerates devices now rather than waiting for the next poll or OS
+    /// device notification, see
+    /// [`Subscription::refresh`](crate::Subscription::refresh)
+    pub fn refresh(&self) {
+        // A full channel already has a refresh pending
+        let _ = self.tx_close.try_send(Signal::Refresh);
+    }
+
+    /// Returns a handle to the background thread's poll statistics
+    pub fn stats(&self) -> Arc<ObserverStats> {
+        self.stats.clone()
+    }
+}
+
+/// A subscription to the events of an [`EnumerationSource`]
+///
+/// This is like a [`Subscription`](crate::Subscription), except that the
+/// background thread is shared so it can only be refreshed or stopped
+/// through the source.
+#[cfg_attr(
+    not(any(feature = "std-channels", not(feature = "crossbeam"))),
+    derive(Clone)
+)]
+pub struct SourceSubscription {
+    pub rx_event: Receiver<Event>,
+    stats: Arc<ObserverStats>,
+}
+
+impl SourceSubscription {
+    /// Returns a handle to the shared background thread's poll statistics
+    pub fn stats(&self) -> Arc<ObserverStats> {
+        self.stats.clone()
+    }
+
+    /// Returns `true` while the shared background thread is running
+    pub fn is_alive(&self) -> bool {
+        self.stats.is_alive()
+    }
+
+    /// Blocks until the next event is received
+    ///
+    /// Returns an error once the source has been dropped.
+    pub fn recv(&self) -> Result<Event, RecvError> {
+        self.rx_event.recv()
+    }
+
+    /// Returns the next event if one has already been received
+    pub fn try_recv(&self) -> Result<Event, TryRecvError> {
+        self.rx_event.try_recv()
+    }
+
+    /// Waits up to `timeout` for the next event
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Event, RecvTimeoutError> {
+        self.rx_event.recv_timeout(timeout)
+    }
+
+    /// Iterates over the events that have already been received without
+    /// blocking
+    pub fn try_iter(&self) -> impl Iterator<Item = Event> + '_ {
+        self.rx_event.try_iter()
+    }
+}
+
+impl Shared {
+    /// Tracks the connected devices and sends the event to the subscribers
+    /// it is relevant to, dropping those that have been dropped
+    fn send(&mut self, event: Event) {
+        let devices = self.devices.get_or_insert_with(Vec::new);
+
+        match &event {
+            Event::Initial(initial) => *devices = initial.clone(),
+            Event::Connect(device) => devices.push(device.clone()),
+            Event::Disconnect(device) => devices.retain(|d| d != device),
+            Event::Reconnect(device) => reconnect(devices, device),
+            Event::Changed { previous, current } => replace(devices, previous, current),
+            Event::Batch {
+                connected,
+                disconnected,
+                changed,
+                reconnected,
+            } => {
+                devices.retain(|d| !disconnected.contains(d));
+                devices.extend(connected.iter().cloned());
+                for (previous, current) in changed {
+                    replace(devices, previous, current);
+                }
+                for device in reconnected {
+                    reconnect(devices, device);
+                }
+            }
+        }
+
+        self.subscribers
+            .retain(|subscriber| subscriber.send(&event));
+    }
+}
+
+impl Subscriber {
+    /// Sends the part of the event for this subscriber's devices, returning
+    /// `false` if the subscription has been dropped
+    fn send(&self, event: &Event) -> bool {
+        match self.filter(event) {
+            Some(event) => self.tx_event.send(event).is_ok(),
+            None => true,
+        }
+    }
+
+    fn filter(&self, event: &Event) -> Option<Event> {
+        let matches = |device: &UsbDevice| device.matches(self.vendor_id, self.product_id);
+        let matching = |devices: &[UsbDevice]| -> Vec<UsbDevice> {
+            devices.iter().filter(|d| matches(d)).cloned().collect()
+        };
+
+        match event {
+            Event::Initial(devices) => Some(Event::Initial(matching(devices))),
+            Event::Connect(device) | Event::Disconnect(device) | Event::Reconnect(device) => {
+                Some(event.clone()).filter(|_| matches(device))
+            }
+            Event::Changed { current, .. } => Some(event.clone()).filter(|_| matches(current)),
+            Event::Batch {
+                connected,
+                disconnected,
+                changed,
+                reconnected,
+            } => {
+                let event = Event::Batch {
+                    connected: matching(connected),
+                    disconnected: matching(disconnected),
+                    changed: changed
+                        .iter()
+                        .filter(|(_, current)| matches(current))
+                        .cloned()
+                        .collect(),
+                    reconnected: matching(reconnected),
+                };
+
+                match &event {
+                    Event::Batch {
+                        connected,
+                        disconnected,
+                        changed,
+                        reconnected,
+                    } if connected.is_empty()
+                        && disconnected.is_empty()
+                        && changed.is_empty()
+                        && reconnected.is_empty() =>
+                    {
+                        None
+                    }
+                    _ => Some(event),
+                }
+            }
+        }
+    }
+}
+
+fn replace(devices: &mut [UsbDevice], previous: &UsbDevice, current: &UsbDevice) {
+    if let Some(device) = devices.iter_mut().find(|d| *d == previous) {
+        *device = current.clone();
+    }
+}
+
+/// Replaces the device from before a reconnect, which is matched by
+/// [`UsbDevice::stable_id`] if its `id` changed
+fn reconnect(devices: &mut Vec<UsbDevice>, device: &UsbDevice) {
+    let position = devices.iter().position(|d| d == device).or_else(|| {
+        devices
+            .iter()
+            .position(|d| d.stable_id() == device.stable_id())
+    });
+
+    match position {
+        Some(i) => devices[i] = device.clone(),
+        None => devices.push(device.clone()),
+    }
+}
+
+fn lock(shared: &Mutex<Shared>) -> std::sync::MutexGuard<'_, Shared> {
+    shared
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{scripted_source, test_device};
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    fn device(id: &str, vendor_id: u16) -> UsbDevice {
+        UsbDevice {
+            vendor_id,
+            ..test_device(id, "Device")
+        }
+    }
+
+    #[test]
+    fn test_enumeration_source() {
+        let a = device("a", 0x1111);
+        let b = device("b", 0x2222);
+
+        let script = scripted_source(vec![
+            vec![a.clone()],
+            vec![a.clone(), b.clone()],
+            vec![b.clone()],
+        ]);
+        let subscribed = Arc::new(AtomicBool::new(false));
+
+        let source = EnumerationSource::from_observer(
+            Observer::new()
+                .with_poll_interval_duration(Duration::from_millis(10))
+                .with_source({
+                    let (a, subscribed) = (a.clone(), subscribed.clone());
+                    // Wait for the subscriptions before changing the devices
+                    move |vendor_id, product_id| {
+                        if subscribed.load(Ordering::SeqCst) {
+                            script(vendor_id, product_id)
+                        } else {
+                            Ok(vec![a.clone()])
+                        }
+                    }
+                }),
+        );
+
+        let subscription_a = source.subscribe(Some(0x1111), None);
+        let subscription_b = source.subscribe(Some(0x2222), None);
+        subscribed.store(true, Ordering::SeqCst);
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            subscription_a.recv_timeout(timeout),
+            Ok(Event::Initial(vec![a.clone()]))
+        );
+        assert_eq!(
+            subscription_a.recv_timeout(timeout),
+            Ok(Event::Disconnect(a.clone()))
+        );
+
+        assert_eq!(
+            subscription_b.recv_timeout(timeout),
+            Ok(Event::Initial(Vec::new()))
+        );
+        assert_eq!(
+            subscription_b.recv_timeout(timeout),
+            Ok(Event::Connect(b.clone()))
+        );
+
+        // Late subscribers start with the devices connected now
+        let late = source.subscribe(None, None);
+        assert_eq!(late.recv_timeout(timeout), Ok(Event::Initial(vec![b])));
+
+        // Dropping the source ends its subscriptions
+        drop(source);
+        assert!(subscription_a.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    fn test_reconnect_replaces_device() {
+        let a = device("a", 0x1111);
+        let renamed = UsbDevice {
+            description: Some("Renamed".to_string()),
+            ..a.clone()
+        };
+
+        let mut shared = Shared::default();
+        shared.send(Event::Initial(vec![a]));
+        shared.send(Event::Reconnect(renamed.clone()));
+
+        let devices = shared.devices.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert!(devices[0].eq_all_fields(&renamed));
+    }
+}
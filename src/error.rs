@@ -0,0 +1,157 @@
+use crate::{DeviceError, EnumerationError, PermissionError, VidPidParseError};
+use std::{any::Any, fmt};
+
+/// # Any error returned by this crate
+///
+/// Every fallible function returns this, so callers can match on one type
+/// rather than a boxed trait object. The more specific error types convert
+/// into it with `?`.
+///
+/// ```no_run
+/// fn connected(vid_pid: &str) -> Result<bool, usb_enumeration::Error> {
+///     let vid_pid: usb_enumeration::VidPid = vid_pid.parse()?;
+///     Ok(usb_enumeration::enumerate_one(vid_pid)?.is_some())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A platform call failed, see [`EnumerationError`]
+    Os {
+        /// Platform specific error code
+        code: i64,
+        /// Name of the platform call that failed
+        call: &'static str,
+    },
+    /// A device was found but couldn't be read
+    Device(DeviceError),
+    /// A Vendor and Product ID couldn't be parsed
+    Parse(VidPidParseError),
+    /// The process can't fully read USB devices
+    Permission(PermissionError),
+    /// USB enumeration isn't supported on this platform
+    Unsupported,
+    /// Enumeration panicked, with the panic message if it had one
+    ///
+    /// Only an `Observer` catches panics, so this is only seen in its
+    /// [`last_error`](crate::ObserverStats::last_error) and logs.
+    Panicked(String),
+    /// The async runtime shut down before enumeration finished
+    Cancelled,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Os { code, call } => EnumerationError::new(call, *code).fmt(f),
+            Error::Device(e) => e.fmt(f),
+            Error::Parse(e) => e.fmt(f),
+            Error::Permission(e) => e.fmt(f),
+            Error::Unsupported => write!(f, "USB enumeration isn't supported on this platform"),
+            Error::Panicked(message) => write!(f, "USB enumeration panicked: {}", message),
+            Error::Cancelled => write!(f, "USB enumeration was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Os { .. } | Error::Unsupported | Error::Panicked(_) | Error::Cancelled => None,
+            Error::Device(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            Error::Permission(e) => Some(e),
+        }
+    }
+}
+
+impl Error {
+    /// Builds an [`Error::Panicked`] from a caught panic's payload
+    #[cfg_attr(not(feature = "observer"), allow(dead_code))]
+    pub(crate) fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload
+                .downcast_ref::<&str>()
+                .map_or_else(String::new, |message| message.to_string()),
+        };
+        Error::Panicked(message)
+    }
+}
+
+impl From<EnumerationError> for Error {
+    fn from(e: EnumerationError) -> Self {
+        Error::Os {
+            code: e.code,
+            call: e.call,
+        }
+    }
+}
+
+impl From<DeviceError> for Error {
+    fn from(e: DeviceError) -> Self {
+        Error::Device(e)
+    }
+}
+
+impl From<VidPidParseError> for Error {
+    fn from(e: VidPidParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+/// Enumeration failures keep their own variant rather than being nested
+impl From<PermissionError> for Error {
+    fn from(e: PermissionError) -> Self {
+        match e {
+            PermissionError::Enumeration(e) => e.into(),
+            e => Error::Permission(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from() {
+        let error = Error::from(EnumerationError::new("SetupDiGetClassDevsW", 5));
+        assert_eq!(
+            error,
+            Error::Os {
+                code: 5,
+                call: "SetupDiGetClassDevsW"
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "SetupDiGetClassDevsW failed with error code 5"
+        );
+
+        let error = Error::from(PermissionError::Enumeration(EnumerationError::new(
+            "Enumerator::new",
+            13,
+        )));
+        assert!(matches!(error, Error::Os { code: 13, .. }));
+
+        let error: Error = "046d".parse::<crate::VidPid>().unwrap_err().into();
+        assert!(matches!(error, Error::Parse(_)));
+
+        assert_eq!(
+            Error::Unsupported.to_string(),
+            "USB enumeration isn't supported on this platform"
+        );
+    }
+
+    #[test]
+    fn test_from_panic() {
+        let error =
+            Error::from_panic(std::panic::catch_unwind(|| panic!("bad device")).unwrap_err());
+        assert_eq!(error, Error::Panicked("bad device".to_string()));
+        assert_eq!(error.to_string(), "USB enumeration panicked: bad device");
+
+        let error =
+            Error::from_panic(std::panic::catch_unwind(|| panic!("device {}", 3)).unwrap_err());
+        assert_eq!(error, Error::Panicked("device 3".to_string()));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::common::*;
+
+use std::{collections::HashMap, process::Command, time::Duration};
+
+/// Enumerates devices by parsing the device and configuration descriptors
+/// dumped by `usbconfig`, which needs read access to `/dev/usb`
+pub fn enumerate_platform(
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<impl Iterator<Item = Result<UsbDevice, DeviceError>>, crate::Error> {
+    let output = Command::new("usbconfig")
+        .args(["dump_device_desc", "dump_curr_config_desc"])
+        .output()
+        .map_err(|e| EnumerationError::new("usbconfig", e.raw_os_error().unwrap_or_default()))?;
+
+    if !output.status.success() {
+        return Err(crate::Error::from(EnumerationError::new(
+            "usbconfig",
+            output.status.code().unwrap_or_default(),
+        )));
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    Ok(device_blocks(&output)
+        .into_iter()
+        .filter_map(move |block| usb_device_from(&block, vid, pid).transpose()))
+}
+
+/// Splits `usbconfig` output into one block per device, each starting with a
+/// line like `ugen0.2: <Logitech USB Receiver> at usbus0, ...`
+fn device_blocks(output: &str) -> Vec<String> {
+    let mut blocks: Vec<String> = Vec::new();
+
+    for line in output.lines() {
+        if line.starts_with("ugen") {
+            blocks.push(String::new());
+        }
+
+        if let Some(block) = blocks.last_mut() {
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+
+    blocks
+}
+
+/// Reads a `usbconfig` device block, returning `None` if it doesn't match the
+/// filters
+fn usb_device_from(
+    block: &str,
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<Option<UsbDevice>, DeviceError> {
+    let header = block.lines().next().unwrap_or_default();
+
+    let id = header
+        .split_once(':')
+        .map(|(id, _)| id.to_string())
+        .ok_or_else(|| DeviceError::new(header, "header", "device name missing"))?;
+
+    let error = |step, message: &str| DeviceError::new(id.as_str(), step, message);
+
+    let vendor_id = field_value(block, "idVendor")
+        .map(|value| value as u16)
+        .ok_or_else(|| error("idVendor", "field missing"))?;
+
+    if let Some(vid) = vid {
+        if vid != vendor_id {
+            return Ok(None);
+        }
+    }
+
+    let product_id = field_value(block, "idProduct")
+        .map(|value| value as u16)
+        .ok_or_else(|| error("idProduct", "field missing"))?;
+
+    if let Some(pid) = pid {
+        if pid != product_id {
+            return Ok(None);
+        }
+    }
+
+    // The header has the product string, or the IDs when there isn't one
+    let description = field_string(block, "iProduct")
+        .or_else(|| {
+            header
+                .split_once('<')
+                .and_then(|(_, rest)| rest.split_once('>'))
+                .map(|(description, _)| description.to_string())
+        })
+        .as_deref()
+        .and_then(clean_description);
+
+    let (bus_number, device_address) = bus_and_address_from_id(&id).unzip();
+
+    Ok(Some(UsbDevice {
+        vendor_id,
+        product_id,
+        description,
+        product_name: None,
+        manufacturer: field_string(block, "iManufacturer"),
+        serial_number: field_string(block, "iSerialNumber"),
+        base_class: field_value(block, "bDeviceClass")
+            .map(|class| DeviceBaseClass::from(class as u8)),
+        sub_class: field_value(block, "bDeviceSubClass").map(|class| class as u8),
+        protocol: field_value(block, "bDeviceProtocol").map(|protocol| protocol as u8),
+        speed: speed_from_header(header),
+        bus_number,
+        device_address,
+        interface_number: None,
+        device_version: field_value(block, "bcdDevice").map(|bcd| version_from_bcd(bcd as u16)),
+        usb_version: field_value(block, "bcdUSB").map(|bcd| version_from_bcd(bcd as u16)),
+        device_path: Some(format!("/dev/{}", id)),
+        max_power_ma: None,
+        self_powered: None,
+        driver: None,
+        parent_id: None,
+        port_number: None,
+        num_configurations: field_value(block, "bNumConfigurations").map(|count| count as u8),
+        interfaces: interfaces_from(block),
+        location_id: None,
+        removable: None,
+        present: true,
+        hardware_ids: Vec::new(),
+        raw_properties: raw_properties(block),
+        // From the current configuration descriptor
+        active_configuration: field_value(block, "bConfigurationValue").map(|value| value as u8),
+        id,
+    }))
+}
+
+/// Reads the interfaces from the configuration descriptor, where each one
+/// starts with a line like `    Interface 0`
+fn interfaces_from(block: &str) -> Vec<UsbInterface> {
+    let mut sections: Vec<String> = Vec::new();
+
+    for line in block.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Interface ") || trimmed.starts_with("Endpoint ") {
+            sections.push(String::new());
+        }
+
+        if let Some(section) = sections.last_mut() {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+
+    sections
+        .iter()
+        .filter(|section| section.trim_start().starts_with("Interface "))
+        // Alternate settings repeat the interface
+        .filter(|section| field_value(section, "bAlternateSetting").unwrap_or_default() == 0)
+        .filter_map(|section| {
+            Some(UsbInterface {
+                number: field_value(section, "bInterfaceNumber")? as u8,
+                base_class: DeviceBaseClass::from(field_value(section, "bInterfaceClass")? as u8),
+                sub_class: field_value(section, "bInterfaceSubClass")? as u8,
+                protocol: field_value(section, "bInterfaceProtocol")? as u8,
+                description: field_string(section, "iInterface")
+                    .as_deref()
+                    .and_then(clean_description),
+            })
+        })
+        .collect()
+}
+
+/// Reads every device descriptor field when the `raw-properties` feature is
+/// enabled, stopping at the configuration descriptor
+fn raw_properties(block: &str) -> Option<HashMap<String, String>> {
+    if !cfg!(feature = "raw-properties") {
+        return None;
+    }
+
+    Some(
+        block
+            .lines()
+            .take_while(|line| !line.trim_start().starts_with("Configuration index"))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect(),
+    )
+}
+
+/// Finds a descriptor field line like `  idVendor = 0x046d `
+fn field<'a>(block: &'a str, name: &str) -> Option<&'a str> {
+    block.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() == name {
+            Some(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+/// Parses the hex value of a descriptor field
+fn field_value(block: &str, name: &str) -> Option<u32> {
+    let value = field(block, name)?.split_whitespace().next()?;
+    u32::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+/// Parses the string after a descriptor index, like
+/// `iProduct = 0x0002  <USB Receiver>`
+fn field_string(block: &str, name: &str) -> Option<String> {
+    let (_, rest) = field(block, name)?.split_once('<')?;
+    let (value, _) = rest.rsplit_once('>')?;
+
+    if value.is_empty() || value == "no string" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Parses the speed from a header like `... md=HOST spd=FULL (12Mbps) ...`
+fn speed_from_header(header: &str) -> Option<UsbSpeed> {
+    let speed = header
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix("spd="))?;
+
+    Some(match speed {
+        "LOW" => UsbSpeed::Low,
+        "FULL" => UsbSpeed::Full,
+        "HIGH" => UsbSpeed::High,
+        "SUPER" => UsbSpeed::Super,
+        _ => UsbSpeed::Unknown,
+    })
+}
+
+/// Parses the bus and address from a device name like `ugen0.2`
+fn bus_and_address_from_id(id: &str) -> Option<(u8, u8)> {
+    let (bus, address) = id.strip_prefix("ugen")?.split_once('.')?;
+    Some((bus.parse().ok()?, address.parse().ok()?))
+}
+
+/// Hubs only report their number of ports when opened
+pub fn hub_port_count(_device: &UsbDevice) -> Option<u8> {
+    None
+}
+
+/// There are no OS device notifications on FreeBSD yet so `Observer`s poll
+pub struct HotplugMonitor;
+
+impl HotplugMonitor {
+    pub fn new() -> Option<Self> {
+        None
+    }
+
+    pub fn wait(&mut self, _timeout: Duration) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+ugen0.1: <0x8086 XHCI root HUB> at usbus0, cfg=0 md=HOST spd=SUPER (5.0Gbps) pwr=SAVE (0mA)
+
+  bLength = 0x0012
+  bDescriptorType = 0x0001
+  bcdUSB = 0x0300
+  bDeviceClass = 0x0009  <HUB>
+  idVendor = 0x8086
+  idProduct = 0x0000
+  iManufacturer = 0x0001  <0x8086>
+  iProduct = 0x0002  <XHCI root HUB>
+  iSerialNumber = 0x0000  <no string>
+
+ugen0.2: <Logitech USB Receiver> at usbus0, cfg=0 md=HOST spd=FULL (12Mbps) pwr=ON (98mA)
+
+  bLength = 0x0012
+  bcdUSB = 0x0200
+  bDeviceClass = 0x0000  <Probed by interface class>
+  bDeviceSubClass = 0x0000
+  bDeviceProtocol = 0x0000
+  idVendor = 0x046d
+  idProduct = 0xc52b
+  bcdDevice = 0x1201
+  iManufacturer = 0x0001  <Logitech>
+  iProduct = 0x0002  <USB Receiver>
+  iSerialNumber = 0x0003  <ABC123>
+  bNumConfigurations = 0x0001
+
+ Configuration index 0
+
+    bLength = 0x0009
+    bNumInterfaces = 0x0002
+    bConfigurationValue = 0x0001
+    bMaxPower = 0x0031
+
+    Interface 0
+      bInterfaceNumber = 0x0000
+      bAlternateSetting = 0x0000
+      bInterfaceClass = 0x0003  <HID device>
+      bInterfaceSubClass = 0x0001
+      bInterfaceProtocol = 0x0001
+      iInterface = 0x0000  <no string>
+
+     Endpoint 0
+        bEndpointAddress = 0x0081  <IN>
+
+    Interface 1
+      bInterfaceNumber = 0x0001
+      bAlternateSetting = 0x0000
+      bInterfaceClass = 0x0003  <HID device>
+      bInterfaceSubClass = 0x0000
+      bInterfaceProtocol = 0x0000
+      iInterface = 0x0005  <Receiver>
+
+    Interface 1 Alt 1
+      bInterfaceNumber = 0x0001
+      bAlternateSetting = 0x0001
+      bInterfaceClass = 0x0003  <HID device>
+      bInterfaceSubClass = 0x0000
+      bInterfaceProtocol = 0x0000
+      iInterface = 0x0000  <no string>
+";
+
+    #[test]
+    fn test_usb_device_from() {
+        let blocks = device_blocks(OUTPUT);
+        assert_eq!(blocks.len(), 2);
+
+        let device = usb_device_from(&blocks[1], None, None).unwrap().unwrap();
+        assert_eq!(device.id, "ugen0.2");
+        assert_eq!(device.vendor_id, 0x046d);
+        assert_eq!(device.product_id, 0xc52b);
+        assert_eq!(device.description.as_deref(), Some("USB Receiver"));
+        assert_eq!(device.manufacturer.as_deref(), Some("Logitech"));
+        assert_eq!(device.serial_number.as_deref(), Some("ABC123"));
+        assert_eq!(device.speed, Some(UsbSpeed::Full));
+        assert_eq!(device.bus_number, Some(0));
+        assert_eq!(device.device_address, Some(2));
+        assert_eq!(device.device_version, Some((12, 1)));
+        assert_eq!(device.num_configurations, Some(1));
+        assert_eq!(device.active_configuration, Some(1));
+        assert_eq!(
+            device.interfaces,
+            vec![
+                UsbInterface {
+                    number: 0,
+                    base_class: DeviceBaseClass::HumanInterfaceDevice,
+                    sub_class: 1,
+                    protocol: 1,
+                    description: None,
+                },
+                UsbInterface {
+                    number: 1,
+                    base_class: DeviceBaseClass::HumanInterfaceDevice,
+                    sub_class: 0,
+                    protocol: 0,
+                    description: Some("Receiver".to_string()),
+                },
+            ]
+        );
+
+        let hub = usb_device_from(&blocks[0], None, None).unwrap().unwrap();
+        assert_eq!(hub.base_class, Some(DeviceBaseClass::Hub));
+        assert_eq!(hub.serial_number, None);
+
+        assert!(usb_device_from(&blocks[1], Some(0x1234), None)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::UsbDevice;
+use std::collections::BTreeMap;
+
+/// # Groups devices by their Vendor ID
+///
+/// The map is ordered by Vendor ID and devices keep their order within each
+/// group.
+///
+/// ```no_run
+/// let devices = usb_enumeration::enumerate(None, None)?;
+///
+/// for (vendor_id, devices) in usb_enumeration::group_by_vendor(devices) {
+///     println!("{:04x}: {} devices", vendor_id, devices.len());
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn group_by_vendor(devices: Vec<UsbDevice>) -> BTreeMap<u16, Vec<UsbDevice>> {
+    let mut groups: BTreeMap<u16, Vec<UsbDevice>> = BTreeMap::new();
+
+    for device in devices {
+        groups.entry(device.vendor_id).or_default().push(device);
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_device;
+
+    fn device(id: &str, vendor_id: u16) -> UsbDevice {
+        UsbDevice {
+            vendor_id,
+            ..test_device(id, "Device")
+        }
+    }
+
+    #[test]
+    fn test_group_by_vendor() {
+        let groups = group_by_vendor(vec![
+            device("b", 0x046d),
+            device("a", 0x1d6b),
+            device("c", 0x046d),
+        ]);
+
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), [0x046d, 0x1d6b]);
+        let ids: Vec<&str> = groups[&0x046d].iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, ["b", "c"]);
+        assert_eq!(groups[&0x1d6b].len(), 1);
+    }
+}
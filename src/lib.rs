@@ -5,7 +5,7 @@
 //!
 //! # Example
 //! ```no_run
-//! let devices = usb_enumeration::enumerate(None, None);
+//! let devices = usb_enumeration::enumerate(None, None)?;
 //!
 //! println!("{:#?}", devices);
 //!
@@ -18,6 +18,60 @@
 //! //       description: Some(
 //! //           "PicoScope 4000 series PC Oscilloscope",
 //! //       ),
+//! //       product_name: None,
+//! //       manufacturer: Some(
+//! //           "Pico Technology",
+//! //       ),
+//! //       serial_number: Some(
+//! //           "0000000004BE",
+//! //       ),
+//! //       base_class: Some(
+//! //           VendorSpecific,
+//! //       ),
+//! //       sub_class: Some(
+//! //           0,
+//! //       ),
+//! //       protocol: Some(
+//! //           0,
+//! //       ),
+//! //       speed: None,
+//! //       bus_number: None,
+//! //       device_address: None,
+//! //       interface_number: None,
+//! //       device_version: Some(
+//! //           (
+//! //               1,
+//! //               0,
+//! //           ),
+//! //       ),
+//! //       usb_version: None,
+//! //       device_path: Some(
+//! //           "USB\\VID_0CE9&PID_1220\\0000000004BE",
+//! //       ),
+//! //       max_power_ma: None,
+//! //       self_powered: None,
+//! //       driver: Some(
+//! //           "usbccgp",
+//! //       ),
+//! //       parent_id: Some(
+//! //           "USB\\ROOT_HUB30\\4&2A4F5B39&0&0",
+//! //       ),
+//! //       port_number: Some(
+//! //           3,
+//! //       ),
+//! //       num_configurations: None,
+//! //       interfaces: [],
+//! //       location_id: None,
+//! //       removable: Some(
+//! //           true,
+//! //       ),
+//! //       present: true,
+//! //       hardware_ids: [
+//! //           "USB\\VID_0CE9&PID_1220&REV_0100",
+//! //           "USB\\VID_0CE9&PID_1220",
+//! //       ],
+//! //       raw_properties: None,
+//! //       active_configuration: None,
 //! //   },
 //! //   UsbDevice {
 //! //       id: "USB\\VID_046D&PID_C52B\\5&17411534&0&11",
@@ -26,6 +80,69 @@
 //! //       description: Some(
 //! //           "USB Composite Device",
 //! //       ),
+//! //       product_name: None,
+//! //       manufacturer: Some(
+//! //           "Logitech",
+//! //       ),
+//! //       serial_number: None,
+//! //       base_class: Some(
+//! //           UseClassCodeFromInterfaceDescriptors,
+//! //       ),
+//! //       sub_class: Some(
+//! //           0,
+//! //       ),
+//! //       protocol: Some(
+//! //           0,
+//! //       ),
+//! //       speed: None,
+//! //       bus_number: None,
+//! //       device_address: None,
+//! //       interface_number: None,
+//! //       device_version: Some(
+//! //           (
+//! //               12,
+//! //               3,
+//! //           ),
+//! //       ),
+//! //       usb_version: None,
+//! //       device_path: Some(
+//! //           "USB\\VID_046D&PID_C52B\\5&17411534&0&11",
+//! //       ),
+//! //       max_power_ma: None,
+//! //       self_powered: None,
+//! //       driver: Some(
+//! //           "usbccgp",
+//! //       ),
+//! //       parent_id: Some(
+//! //           "USB\\ROOT_HUB30\\4&2A4F5B39&0&0",
+//! //       ),
+//! //       port_number: Some(
+//! //           11,
+//! //       ),
+//! //       num_configurations: None,
+//! //       interfaces: [
+//! //           UsbInterface {
+//! //               number: 0,
+//! //               base_class: HumanInterfaceDevice,
+//! //               sub_class: 0,
+//! //               protocol: 0,
+//! //               description: Some(
+//! //                   "Logitech USB Input Device",
+//! //               ),
+//! //           },
+//! //             etc...
+//! //       ],
+//! //       location_id: None,
+//! //       removable: Some(
+//! //           true,
+//! //       ),
+//! //       present: true,
+//! //       hardware_ids: [
+//! //           "USB\\VID_046D&PID_C52B&REV_1203",
+//! //           "USB\\VID_046D&PID_C52B",
+//! //       ],
+//! //       raw_properties: None,
+//! //       active_configuration: None,
 //! //   },
 //! //   UsbDevice {
 //! //       id: "USB\\VID_046D&PID_C52B&MI_00\\6&12D311A2&0&0000",
@@ -34,12 +151,67 @@
 //! //       description: Some(
 //! //           "Logitech USB Input Device",
 //! //       ),
+//! //       product_name: None,
+//! //       manufacturer: Some(
+//! //           "Logitech",
+//! //       ),
+//! //       serial_number: None,
+//! //       base_class: Some(
+//! //           HumanInterfaceDevice,
+//! //       ),
+//! //       sub_class: Some(
+//! //           0,
+//! //       ),
+//! //       protocol: Some(
+//! //           0,
+//! //       ),
+//! //       speed: None,
+//! //       bus_number: None,
+//! //       device_address: None,
+//! //       interface_number: Some(
+//! //           0,
+//! //       ),
+//! //       device_version: Some(
+//! //           (
+//! //               12,
+//! //               3,
+//! //           ),
+//! //       ),
+//! //       usb_version: None,
+//! //       device_path: Some(
+//! //           "USB\\VID_046D&PID_C52B&MI_00\\6&12D311A2&0&0000",
+//! //       ),
+//! //       max_power_ma: None,
+//! //       self_powered: None,
+//! //       driver: Some(
+//! //           "HidUsb",
+//! //       ),
+//! //       parent_id: Some(
+//! //           "USB\\VID_046D&PID_C52B\\5&17411534&0&11",
+//! //       ),
+//! //       port_number: None,
+//! //       num_configurations: None,
+//! //       interfaces: [],
+//! //       location_id: None,
+//! //       removable: Some(
+//! //           true,
+//! //       ),
+//! //       present: true,
+//! //       hardware_ids: [
+//! //           "USB\\VID_046D&PID_C52B&REV_1203&MI_00",
+//! //           "USB\\VID_046D&PID_C52B&MI_00",
+//! //       ],
+//! //       raw_properties: None,
+//! //       active_configuration: None,
 //! //   },
 //! //     etc...
 //! // ]
+//! # Ok::<(), usb_enumeration::Error>(())
 //! ```
-//! You can also subscribe to events using the `Observer`:
+//! With the default `observer` feature you can also subscribe to events using
+//! the `Observer`:
 //! ```no_run
+//! # #[cfg(feature = "observer")] {
 //! use usb_enumeration::{Observer, Event};
 //!
 //! let sub = Observer::new()
@@ -55,211 +227,799 @@
 //!         Event::Initial(d) => println!("Initial devices: {:?}", d),
 //!         Event::Connect(d) => println!("Connected device: {:?}", d),
 //!         Event::Disconnect(d) => println!("Disconnected device: {:?}", d),
+//!         _ => {}
 //!     }   
 //! }
+//! # }
 //! ```
+//!
+//! # Features
+//!
+//! - `crossbeam` (default) - Uses crossbeam channels for `Subscription`, which
+//!   allows it to be cloned
+//! - `std-channels` - Uses `std::sync::mpsc` channels for `Subscription`
+//!   instead of crossbeam. Disable default features to drop the crossbeam
+//!   dependency
+//! - `observer` (default) - Adds the `Observer`, which watches for device
+//!   changes on a background thread. Disable default features for just
+//!   `enumerate` and the other one-shot functions
+//! - `log` - Logs what each enumeration found and why devices were skipped
+//!   using the `log` crate
+//! - `mock` - Adds `set_mock_devices` and related functions to replace the OS
+//!   devices with mock devices so code using this crate can be tested without
+//!   hardware
+//! - `raw-properties` - Fills in `UsbDevice::raw_properties` with every property
+//!   the OS reports, for properties this crate doesn't model
+//! - `rusb` - Adds `UsbDevice::open_rusb` for opening a device with `rusb`,
+//!   matching it by bus number and address where available, and
+//!   `UsbDevice::read_strings` for reading its live string descriptors
+//! - `serde` - Derives `Serialize` and `Deserialize` for `UsbDevice` and `Event`,
+//!   and adds `serde_hex` for writing Vendor and Product IDs as hex strings
+//! - `stream` - Adds `Subscription::into_stream` for consuming events as an
+//!   async `Stream`
+//! - `tokio` - Adds `enumerate_async`, which enumerates on tokio's blocking
+//!   thread pool
+//! - `wasm` - Adds `enumerate_webusb` and `watch_webusb` for wasm32 targets, which
+//!   only see devices the user has authorized. Needs
+//!   `RUSTFLAGS="--cfg=web_sys_unstable_apis"`
+//! - `usb-ids` - Fills in `manufacturer` and `product_name` from the bundled
+//!   `usb.ids` database when the OS doesn't provide them
 
 #![cfg_attr(feature = "strict", deny(warnings))]
 
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "observer")]
+mod callbacks;
+#[cfg(feature = "observer")]
+mod channel;
+// The helpers for reading devices are unused without a platform backend
+#[cfg_attr(
+    not(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "linux",
+        target_os = "freebsd"
+    )),
+    allow(dead_code)
+)]
 mod common;
-pub use common::UsbDevice;
-use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
-use std::{collections::HashSet, thread, time::Duration};
+#[cfg(feature = "observer")]
+pub use callbacks::CallbackGuard;
+#[cfg(feature = "observer")]
+pub use channel::{RecvError, RecvTimeoutError, TryRecvError};
+pub use common::{
+    parse_vid_pid, DeviceBaseClass, DeviceError, EnumerationError, PermissionError, UsbDevice,
+    UsbInterface, UsbSpeed, VidPid, VidPidParseError,
+};
+use std::{thread, time::Duration};
+
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+use mock::enumerate_source;
+#[cfg(feature = "mock")]
+pub use mock::{clear_mock_devices, push_mock_connect, push_mock_disconnect, set_mock_devices};
+#[cfg(feature = "tokio")]
+mod asynchronous;
+#[cfg(feature = "usb-ids")]
+mod names;
+#[cfg(feature = "rusb")]
+mod open;
+#[cfg(feature = "rusb")]
+pub use open::DeviceStrings;
+#[cfg(feature = "stream")]
+mod stream;
+#[cfg(feature = "tokio")]
+pub use asynchronous::enumerate_async;
+mod error;
+pub use error::Error;
+mod group;
+pub use group::group_by_vendor;
+mod options;
+pub use options::EnumerateOptions;
+#[cfg(feature = "observer")]
+mod enumeration_source;
+#[cfg(feature = "serde")]
+pub mod serde_hex;
+#[cfg(feature = "observer")]
+pub use enumeration_source::{EnumerationSource, SourceSubscription};
+#[cfg(feature = "observer")]
+mod observer;
+#[cfg(feature = "observer")]
+pub use observer::{
+    diff, diff_stable, wait_for_device, wait_for_disconnect, Event, Observer, Subscription,
+    TimedEvent,
+};
+#[cfg(feature = "observer")]
+mod stats;
+#[cfg(feature = "observer")]
+pub use stats::ObserverStats;
+mod slice_ext;
+pub use slice_ext::UsbDeviceSliceExt;
+mod topology;
+pub use topology::{build_tree, enumerate_hubs, UsbHub, UsbNode};
 
 #[cfg(target_os = "windows")]
+// The OS device notifications are only used by the Observer
+#[cfg_attr(not(feature = "observer"), allow(dead_code, unused_imports))]
 mod windows;
 #[cfg(target_os = "windows")]
 use crate::windows::*;
 
 #[cfg(target_os = "macos")]
+// The OS device notifications are only used by the Observer
+#[cfg_attr(not(feature = "observer"), allow(dead_code, unused_imports))]
 mod macos;
 #[cfg(target_os = "macos")]
 use crate::macos::*;
 
 #[cfg(target_os = "linux")]
+// The OS device notifications are only used by the Observer
+#[cfg_attr(not(feature = "observer"), allow(dead_code, unused_imports))]
 mod linux;
 #[cfg(target_os = "linux")]
 use crate::linux::*;
 
+#[cfg(target_os = "freebsd")]
+// The OS device notifications are only used by the Observer
+#[cfg_attr(not(feature = "observer"), allow(dead_code, unused_imports))]
+mod freebsd;
+#[cfg(target_os = "freebsd")]
+use crate::freebsd::*;
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32", web_sys_unstable_apis))]
+mod webusb;
+#[cfg(all(feature = "wasm", target_arch = "wasm32", web_sys_unstable_apis))]
+pub use webusb::{enumerate_webusb, watch_webusb, WebUsbWatch};
+#[cfg(all(feature = "wasm", target_arch = "wasm32", not(web_sys_unstable_apis)))]
+compile_error!("The wasm feature needs RUSTFLAGS=\"--cfg=web_sys_unstable_apis\" for WebUSB");
+
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd"
+)))]
+// The OS device notifications are only used by the Observer
+#[cfg_attr(not(feature = "observer"), allow(dead_code, unused_imports))]
+mod unsupported;
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd"
+)))]
+use crate::unsupported::*;
+
 /// # Enumerates connected USB devices
 ///
 /// * `vendor_id` - Optional USB Vendor ID to filter
 /// * `product_id` - Optional USB Product ID to filter
 ///
+/// Returns an error if the platform enumeration API fails.
+///
 /// ```no_run
-/// let devices = usb_enumeration::enumerate(None, None);
+/// let devices = usb_enumeration::enumerate(None, None)?;
+/// # Ok::<(), usb_enumeration::Error>(())
 /// ```
 /// You can also optionally filter by vendor or product ID:
 /// ```no_run
-/// let devices = usb_enumeration::enumerate(Some(0x1234), None);
+/// let devices = usb_enumeration::enumerate(Some(0x1234), None)?;
+/// # Ok::<(), usb_enumeration::Error>(())
 /// ```
-pub fn enumerate(vendor_id: Option<u16>, product_id: Option<u16>) -> Vec<UsbDevice> {
-    enumerate_platform(vendor_id, product_id)
+///
+/// This is a shortcut for [`enumerate_with`].
+pub fn enumerate(vendor_id: Option<u16>, product_id: Option<u16>) -> Result<Vec<UsbDevice>, Error> {
+    enumerate_with(EnumerateOptions {
+        vendor_id,
+        product_id,
+        ..Default::default()
+    })
 }
 
-/// Events send from the Observer
-#[derive(Debug, Clone)]
-pub enum Event {
-    /// Initial list of devices when polling starts
-    Initial(Vec<UsbDevice>),
-    /// A device that has just been connected
-    Connect(UsbDevice),
-    /// A device that has just disconnected
-    Disconnect(UsbDevice),
+/// # Enumerates USB devices matching the options
+///
+/// See [`EnumerateOptions`] for the available filters.
+///
+/// ```no_run
+/// use usb_enumeration::{DeviceBaseClass, EnumerateOptions};
+///
+/// let drives = usb_enumeration::enumerate_with(EnumerateOptions {
+///     class: Some(DeviceBaseClass::MassStorage),
+///     ..Default::default()
+/// })?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_with(options: EnumerateOptions) -> Result<Vec<UsbDevice>, Error> {
+    let devices: Vec<UsbDevice> = enumerate_readable(
+        options.vendor_id,
+        options.product_id,
+        options.include_non_present,
+    )?
+    .filter(|device| options.class.is_none_or(|class| device.has_class(class)))
+    .collect();
+
+    debug!("Enumerated {} devices ({:04x?})", devices.len(), options);
+
+    Ok(devices)
 }
 
-#[derive(Clone)]
-pub struct Subscription {
-    pub rx_event: Receiver<Event>,
-    // When this gets dropped, the channel will become disconnected and the
-    // background thread will close
-    tx_close: Sender<()>,
+/// # Enumerates connected USB devices, retrying if the platform API fails
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+/// * `retries` - How many times to retry after the first attempt fails
+/// * `backoff` - How long to wait before the first retry, doubling for each
+///   retry after that
+///
+/// The platform APIs can fail briefly while the OS is starting up, such as
+/// IOKit right at login on macOS or udev before it is ready on Linux. The
+/// error from the last attempt is returned if every attempt fails.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let devices =
+///     usb_enumeration::enumerate_with_retry(None, None, 3, Duration::from_millis(100))?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_with_retry(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    retries: u32,
+    backoff: Duration,
+) -> Result<Vec<UsbDevice>, Error> {
+    retry(retries, backoff, || enumerate(vendor_id, product_id))
 }
 
-#[derive(Debug, Clone)]
-pub struct Observer {
-    poll_interval: u32,
+/// Calls `f` until it succeeds or has been retried `retries` times, sleeping
+/// between attempts with an exponential backoff
+fn retry<T>(
+    retries: u32,
+    backoff: Duration,
+    mut f: impl FnMut() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut attempt = 0;
+
+    loop {
+        match f() {
+            Err(e) if attempt < retries => {
+                let wait = backoff.saturating_mul(1 << attempt.min(31));
+                warn!("Enumeration failed, retrying in {:?}: {}", wait, e);
+                thread::sleep(wait);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// # Lazily enumerates connected USB devices
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+///
+/// Devices are read from the platform as the iterator is advanced, so
+/// stopping early avoids reading the remaining devices. Returns an error if
+/// the platform enumeration API fails to start.
+///
+/// ```no_run
+/// use usb_enumeration::DeviceBaseClass;
+///
+/// let hub = usb_enumeration::enumerate_iter(None, None)?
+///     .find(|device| device.base_class == Some(DeviceBaseClass::Hub));
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_iter(
     vendor_id: Option<u16>,
     product_id: Option<u16>,
+) -> Result<impl Iterator<Item = UsbDevice>, Error> {
+    enumerate_readable(vendor_id, product_id, false)
 }
 
-impl Default for Observer {
-    fn default() -> Self {
-        Observer::new()
+/// # Enumerates USB devices, including devices that aren't connected
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+///
+/// On Windows this also returns the devices Windows remembers from a
+/// previous connection, such as devices whose drivers are still installed.
+/// These have [`present`](UsbDevice::present) set to `false` and are missing
+/// the details that are only available while connected. Other platforms
+/// only know about connected devices, so this is the same as [`enumerate`].
+///
+/// ```no_run
+/// let devices = usb_enumeration::enumerate_including_non_present(None, None)?;
+///
+/// for device in devices.iter().filter(|device| !device.present) {
+///     println!("Not connected: {}", device);
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_including_non_present(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+) -> Result<Vec<UsbDevice>, Error> {
+    Ok(enumerate_readable(vendor_id, product_id, true)?.collect())
+}
+
+/// Enumerates devices, logging and skipping those that couldn't be read
+fn enumerate_readable(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    include_non_present: bool,
+) -> Result<impl Iterator<Item = UsbDevice>, Error> {
+    Ok(
+        enumerate_devices(vendor_id, product_id, include_non_present)?.filter_map(|result| {
+            match result {
+                Ok(device) => {
+                    trace!(
+                        "Found device {:04x}:{:04x} {}",
+                        device.vendor_id,
+                        device.product_id,
+                        device.id
+                    );
+                    Some(device)
+                }
+                Err(e) => {
+                    warn!("Skipping device: {}", e);
+                    None
+                }
+            }
+        }),
+    )
+}
+
+/// # Enumerates connected USB devices, including devices that couldn't be read
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+///
+/// [`enumerate`] skips devices whose details couldn't be read. This returns
+/// those as errors alongside the devices that were read, which helps when
+/// diagnosing why a device is missing.
+///
+/// ```no_run
+/// let (devices, errors) = usb_enumeration::enumerate_detailed(None, None)?;
+///
+/// for error in errors {
+///     eprintln!("{}", error);
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_detailed(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+) -> Result<(Vec<UsbDevice>, Vec<DeviceError>), Error> {
+    let mut devices = Vec::new();
+    let mut errors = Vec::new();
+
+    for result in enumerate_devices(vendor_id, product_id, false)? {
+        match result {
+            Ok(device) => devices.push(device),
+            Err(e) => errors.push(e),
+        }
     }
+
+    Ok((devices, errors))
 }
 
-impl Observer {
-    /// Create a new Observer with the poll interval specified in seconds
-    pub fn new() -> Self {
-        Observer {
-            poll_interval: 1,
-            vendor_id: None,
-            product_id: None,
+/// # Enumerates connected USB devices in a stable order
+///
+/// Same as [`enumerate`] but the devices are sorted by vendor ID, then
+/// product ID and then `id`, so the order is the same between calls. The
+/// order of [`enumerate`] depends on the platform.
+///
+/// ```no_run
+/// let devices = usb_enumeration::enumerate_sorted(None, None)?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_sorted(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+) -> Result<Vec<UsbDevice>, Error> {
+    let mut devices = enumerate(vendor_id, product_id)?;
+    devices.sort();
+    Ok(devices)
+}
+
+/// # Finds a connected device by its [`id`](UsbDevice::id)
+///
+/// Returns `None` if the device is no longer connected or can't be read.
+///
+/// On Linux the `id` is the sysfs device path, so the device is read directly
+/// without enumerating the others, which makes this cheap to call often.
+/// Other platforms enumerate every device and return the matching one.
+///
+/// ```no_run
+/// # let device = usb_enumeration::enumerate(None, None)?.remove(0);
+/// if let Some(device) = usb_enumeration::find_by_id(&device.id) {
+///     println!("Still connected: {}", device);
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn find_by_id(id: &str) -> Option<UsbDevice> {
+    #[cfg(target_os = "linux")]
+    {
+        #[cfg(feature = "mock")]
+        let direct = !mock::mock_active();
+        #[cfg(not(feature = "mock"))]
+        let direct = true;
+
+        if direct {
+            let device = linux::find_by_id(id)?;
+
+            #[cfg(feature = "usb-ids")]
+            let device = names::fill_names(device);
+
+            return Some(device);
         }
     }
 
-    pub fn with_poll_interval(mut self, seconds: u32) -> Self {
-        self.poll_interval = seconds;
-        self
+    enumerate_devices(None, None, false)
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|device| device.id == id)
+}
+
+/// # Checks whether this process can read USB devices
+///
+/// On Linux this checks that udev provides the vendor and product IDs of
+/// USB devices and that the device nodes of non-hub devices can be opened
+/// for reading and writing, which is what's missing when the udev rules
+/// don't grant access. On other platforms this only checks that devices can
+/// be enumerated.
+///
+/// ```no_run
+/// if let Err(e) = usb_enumeration::check_permissions() {
+///     eprintln!("{}", e);
+/// }
+/// ```
+pub fn check_permissions() -> Result<(), Error> {
+    #[cfg(target_os = "linux")]
+    {
+        Ok(linux::check_permissions()?)
     }
 
-    /// Filter results by USB Vendor ID
-    pub fn with_vendor_id(mut self, vendor_id: u16) -> Self {
-        self.vendor_id = Some(vendor_id);
-        self
+    #[cfg(not(target_os = "linux"))]
+    {
+        enumerate_platform(None, None).map(|_| ())
     }
+}
+
+/// # Enumerates connected USB devices, ignoring errors
+///
+/// Same as [`enumerate`] but returns an empty list if the platform
+/// enumeration API fails.
+///
+/// ```no_run
+/// let devices = usb_enumeration::enumerate_or_empty(None, None);
+/// ```
+pub fn enumerate_or_empty(vendor_id: Option<u16>, product_id: Option<u16>) -> Vec<UsbDevice> {
+    enumerate(vendor_id, product_id).unwrap_or_default()
+}
+
+/// # Finds a connected USB device by Vendor and Product ID
+///
+/// Returns the first matching device, or `None` if none are connected.
+///
+/// ```no_run
+/// let vid_pid = "046d:c52b".parse()?;
+/// let device = usb_enumeration::enumerate_one(vid_pid)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn enumerate_one(vid_pid: VidPid) -> Result<Option<UsbDevice>, Error> {
+    Ok(enumerate_iter(Some(vid_pid.vendor_id), Some(vid_pid.product_id))?.next())
+}
+
+/// # Counts connected USB devices
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+///
+/// Devices are counted as they're enumerated rather than collected, and the
+/// backends skip reading the details of devices that don't match the IDs.
+/// Devices that couldn't be read aren't counted.
+///
+/// ```no_run
+/// let receivers = usb_enumeration::enumerate_count(Some(0x046d), Some(0xc52b))?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_count(vendor_id: Option<u16>, product_id: Option<u16>) -> Result<usize, Error> {
+    Ok(enumerate_iter(vendor_id, product_id)?.count())
+}
+
+/// # Enumerates connected USB devices matching a predicate
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+/// * `predicate` - Called for each device that passes the vendor and product
+///   ID filters. Devices are only returned when this returns `true`
+///
+/// ```no_run
+/// use usb_enumeration::DeviceBaseClass;
+///
+/// let devices = usb_enumeration::enumerate_filtered(None, None, |device| {
+///     device.base_class == Some(DeviceBaseClass::MassStorage)
+/// })?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_filtered(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    predicate: impl Fn(&UsbDevice) -> bool,
+) -> Result<Vec<UsbDevice>, Error> {
+    Ok(enumerate_iter(vendor_id, product_id)?
+        .filter(|device| predicate(device))
+        .collect())
+}
+
+/// # Enumerates connected USB devices with a base class
+///
+/// * `class` - The base class to filter
+///
+/// Devices match when the device or any of its interfaces reports the class,
+/// see [`UsbDevice::has_class`].
+///
+/// ```no_run
+/// use usb_enumeration::DeviceBaseClass;
+///
+/// let drives = usb_enumeration::enumerate_by_class(DeviceBaseClass::MassStorage)?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_by_class(class: DeviceBaseClass) -> Result<Vec<UsbDevice>, Error> {
+    Ok(enumerate_iter(None, None)?
+        .filter(|device| device.has_class(class))
+        .collect())
+}
+
+/// # Enumerates connected USB devices with a serial number
+///
+/// * `serial` - The serial number to match exactly
+///
+/// Devices without a serial number never match. Identical devices usually
+/// only differ by their serial number, so this finds a particular one.
+///
+/// ```no_run
+/// let devices = usb_enumeration::enumerate_by_serial("0000000004BE")?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_by_serial(serial: &str) -> Result<Vec<UsbDevice>, Error> {
+    Ok(enumerate_iter(None, None)?
+        .filter(|device| device.serial_number.as_deref() == Some(serial))
+        .collect())
+}
+
+/// # Enumerates connected USB devices matching any of the given IDs
+///
+/// * `vendor_ids` - USB Vendor IDs to filter, or empty for any vendor
+/// * `product_ids` - USB Product IDs to filter, or empty for any product
+///
+/// A device is included when its vendor ID is one of `vendor_ids` and its
+/// product ID is one of `product_ids`.
+///
+/// ```no_run
+/// // Bootloader and application mode of the same product
+/// let devices = usb_enumeration::enumerate_any(&[0x1234], &[0x0001, 0x0002])?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_any(vendor_ids: &[u16], product_ids: &[u16]) -> Result<Vec<UsbDevice>, Error> {
+    enumerate_matching(vendor_ids, product_ids, false)
+}
+
+fn enumerate_matching(
+    vendor_ids: &[u16],
+    product_ids: &[u16],
+    include_non_present: bool,
+) -> Result<Vec<UsbDevice>, Error> {
+    // Let the platform filter when there's only a single ID
+    Ok(enumerate_readable(
+        single_id(vendor_ids),
+        single_id(product_ids),
+        include_non_present,
+    )?
+    .filter(|device| matches_any(device, vendor_ids, product_ids))
+    .collect())
+}
 
-    /// Filter results by USB Product ID
-    pub fn with_product_id(mut self, product_id: u16) -> Self {
-        self.product_id = Some(product_id);
-        self
+fn single_id(ids: &[u16]) -> Option<u16> {
+    match ids {
+        [id] => Some(*id),
+        _ => None,
     }
+}
 
-    /// Start the background thread and poll for device changes
-    pub fn subscribe(&self) -> Subscription {
-        let (tx_event, rx_event) = unbounded();
-        let (tx_close, rx_close) = bounded::<()>(0);
-
-        thread::Builder::new()
-            .name("USB Enumeration Thread".to_string())
-            .spawn({
-                let this = self.clone();
-                move || {
-                    let device_list = enumerate(this.vendor_id, this.product_id);
-
-                    // Send initially connected devices
-                    if tx_event.send(Event::Initial(device_list.clone())).is_err() {
-                        return;
-                    }
-
-                    let mut device_list: HashSet<UsbDevice> = device_list.into_iter().collect();
-                    let mut wait_seconds = this.poll_interval as f32;
-
-                    loop {
-                        while wait_seconds > 0.0 {
-                            // Check whether the subscription has been disposed
-                            if let Err(crossbeam::channel::RecvTimeoutError::Disconnected) =
-                                rx_close.recv_timeout(Duration::from_millis(250))
-                            {
-                                return;
-                            }
-
-                            wait_seconds -= 0.25;
-                        }
-
-                        wait_seconds = this.poll_interval as f32;
-
-                        let next_devices: HashSet<UsbDevice> =
-                            enumerate(this.vendor_id, this.product_id)
-                                .into_iter()
-                                .collect();
-
-                        // Send Disconnect for missing devices
-                        for device in &device_list {
-                            if !next_devices.contains(&device)
-                                && tx_event.send(Event::Disconnect(device.clone())).is_err()
-                            {
-                                return;
-                            }
-                        }
-
-                        // Send Connect for new devices
-                        for device in &next_devices {
-                            if !device_list.contains(&device)
-                                && tx_event.send(Event::Connect(device.clone())).is_err()
-                            {
-                                return;
-                            }
-                        }
-
-                        device_list = next_devices;
-                    }
-                }
-            })
-            .expect("Could not spawn background thread");
+fn matches_any(device: &UsbDevice, vendor_ids: &[u16], product_ids: &[u16]) -> bool {
+    (vendor_ids.is_empty() || vendor_ids.contains(&device.vendor_id))
+        && (product_ids.is_empty() || product_ids.contains(&device.product_id))
+}
 
-        Subscription { rx_event, tx_close }
+/// Enumerates the platform devices, including the devices Windows remembers
+/// from a previous connection when `include_non_present` is set
+fn enumerate_platform_with(
+    vid: Option<u16>,
+    pid: Option<u16>,
+    include_non_present: bool,
+) -> Result<impl Iterator<Item = Result<UsbDevice, DeviceError>>, Error> {
+    #[cfg(target_os = "windows")]
+    {
+        if include_non_present {
+            return enumerate_non_present(vid, pid);
+        }
     }
+
+    // Other platforms only know about connected devices
+    #[cfg(not(target_os = "windows"))]
+    let _ = include_non_present;
+
+    enumerate_platform(vid, pid)
+}
+
+#[cfg(not(feature = "mock"))]
+fn enumerate_source(
+    vid: Option<u16>,
+    pid: Option<u16>,
+    include_non_present: bool,
+) -> Result<impl Iterator<Item = Result<UsbDevice, DeviceError>>, Error> {
+    enumerate_platform_with(vid, pid, include_non_present)
+}
+
+/// Enumerates devices from the source, filling in names from the USB ID
+/// database when that's enabled
+fn enumerate_devices(
+    vid: Option<u16>,
+    pid: Option<u16>,
+    include_non_present: bool,
+) -> Result<impl Iterator<Item = Result<UsbDevice, DeviceError>>, Error> {
+    let devices = enumerate_source(vid, pid, include_non_present)?;
+
+    #[cfg(feature = "usb-ids")]
+    let devices = devices.map(|result| result.map(names::fill_names));
+
+    Ok(devices)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::test_device;
 
     #[test]
     fn test_enumerate() {
-        let devices = enumerate(None, None);
+        #[cfg(feature = "mock")]
+        let _lock = mock::test_lock();
+        let devices = enumerate(None, None).expect("Enumeration should succeed");
         println!("Enumerated devices: {:#?}", devices);
         assert!(!devices.is_empty());
     }
 
     #[test]
-    fn test_subscribe() {
-        let subscription = Observer::new().subscribe();
-        let mut iter = subscription.rx_event.iter();
+    fn test_device_fields() {
+        // Exhaustive destructuring fails to compile if any platform builds a
+        // device with a different field set
+        let UsbDevice {
+            id: _,
+            vendor_id: _,
+            product_id: _,
+            description: _,
+            manufacturer: _,
+            serial_number: _,
+            base_class: _,
+            speed: _,
+            bus_number: _,
+            device_address: _,
+            interface_number: _,
+            device_version: _,
+            usb_version: _,
+            device_path: _,
+            max_power_ma: _,
+            self_powered: _,
+            driver: _,
+            parent_id: _,
+            port_number: _,
+            sub_class: _,
+            protocol: _,
+            product_name: _,
+            num_configurations: _,
+            interfaces: _,
+            location_id: _,
+            removable: _,
+            present: _,
+            hardware_ids: _,
+            raw_properties: _,
+            active_configuration: _,
+        } = UsbDevice {
+            id: "id".to_string(),
+            vendor_id: 0x1234,
+            product_id: 0x5678,
+            description: None,
+            manufacturer: None,
+            serial_number: None,
+            base_class: None,
+            speed: None,
+            bus_number: None,
+            device_address: None,
+            interface_number: None,
+            device_version: None,
+            usb_version: None,
+            device_path: None,
+            max_power_ma: None,
+            self_powered: None,
+            driver: None,
+            parent_id: None,
+            port_number: None,
+            sub_class: None,
+            protocol: None,
+            product_name: None,
+            num_configurations: None,
+            interfaces: Vec::new(),
+            location_id: None,
+            removable: None,
+            present: true,
+            hardware_ids: Vec::new(),
+            raw_properties: None,
+            active_configuration: None,
+        };
+    }
+
+    #[test]
+    fn test_matches_any() {
+        let device = test_device("1", "Device");
 
-        let initial = iter.next().expect("Should get an Event");
-        assert!(matches!(initial, Event::Initial(_)));
+        assert!(matches_any(&device, &[], &[]));
+        assert!(matches_any(&device, &[0x1111, 0x1234], &[]));
+        assert!(matches_any(&device, &[], &[0x5678, 0x1111]));
+        assert!(matches_any(&device, &[0x1234], &[0x5678]));
+        assert!(!matches_any(&device, &[0x1111], &[]));
+        assert!(!matches_any(&device, &[0x1234], &[0x1111]));
+    }
 
-        println!("Connect a USB device");
+    #[test]
+    fn test_retry() {
+        let mut attempts = 0;
+        let result = retry(2, Duration::from_millis(1), || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(EnumerationError::new("test", attempts).into())
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
 
-        let connect_event = iter.next().expect("Should get an Event");
-        let connect_device = if let Event::Connect(device) = connect_event {
-            device
-        } else {
-            panic!("Expected Event::Connect. Actual: {:?}", connect_event);
-        };
+        let mut attempts = 0;
+        let result: Result<(), _> = retry(1, Duration::from_millis(1), || {
+            attempts += 1;
+            Err(EnumerationError::new("test", attempts).into())
+        });
+        assert_eq!(attempts, 2);
+        assert_eq!(result.unwrap_err(), EnumerationError::new("test", 2).into());
+    }
 
-        println!("Disconnect that same device");
+    #[test]
+    fn test_enumerate_vendor_filter() {
+        #[cfg(feature = "mock")]
+        let _lock = mock::test_lock();
+        let devices = enumerate(None, None).expect("Enumeration should succeed");
+        let vendor_id = devices.first().expect("Should find a device").vendor_id;
 
-        let disconnect_event = iter.next().expect("Should get an Event");
-        let disconnect_device = if let Event::Disconnect(device) = disconnect_event {
-            device
-        } else {
-            panic!("Expected Event::Disconnect. Actual: {:?}", disconnect_event);
-        };
+        let filtered = enumerate(Some(vendor_id), None).expect("Enumeration should succeed");
+        assert!(!filtered.is_empty());
+        assert!(filtered.iter().all(|d| d.vendor_id == vendor_id));
+    }
+
+    #[test]
+    fn test_enumerate_unmatched_vendor_filter() {
+        #[cfg(feature = "mock")]
+        let _lock = mock::test_lock();
+        let devices = enumerate(None, None).expect("Enumeration should succeed");
+        let first = devices.first().expect("Should find a device");
+        let vendor_id = first.vendor_id.wrapping_add(1);
 
-        assert_eq!(connect_device, disconnect_device);
+        let filtered = enumerate(Some(vendor_id), None).expect("Enumeration should succeed");
+        assert!(filtered.iter().all(|d| d.vendor_id == vendor_id));
+        assert!(!filtered.contains(first));
     }
 }
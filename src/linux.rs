@@ -1,74 +1,433 @@
 use crate::common::*;
 
-use std::error::Error;
-use udev::Enumerator;
-
-pub fn enumerate_platform(vid: Option<u16>, pid: Option<u16>) -> Vec<UsbDevice> {
-    let mut output = Vec::new();
-
-    let mut enumerator = Enumerator::new().expect("could not get udev enumerator");
-
-    for device in enumerator.scan_devices().expect("could not scan devices") {
-        let _ = || -> Result<(), Box<dyn Error>> {
-            let vendor_id = get_pid_or_vid(
-                device
-                    .property_value("ID_VENDOR_ID")
-                    .ok_or(ParseError)?
-                    .to_str()
-                    .ok_or(ParseError)?,
-            )?;
-
-            if let Some(vid) = vid {
-                if vid != vendor_id {
-                    return Ok(());
-                }
-            }
-
-            let product_id = get_pid_or_vid(
-                device
-                    .property_value("ID_MODEL_ID")
-                    .ok_or(ParseError)?
-                    .to_str()
-                    .ok_or(ParseError)?,
-            )?;
-
-            if let Some(pid) = pid {
-                if pid != product_id {
-                    return Ok(());
-                }
-            }
-
-            let id = device
-                .property_value("DEVPATH")
-                .ok_or(ParseError)?
-                .to_str()
-                .ok_or(ParseError)?
-                .to_string();
+use std::{
+    collections::HashMap,
+    error::Error,
+    ffi::OsStr,
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    os::unix::io::AsRawFd,
+    path::Path,
+    time::Duration,
+};
+use udev::{Device, Enumerator, MonitorBuilder, MonitorSocket};
+
+pub fn enumerate_platform(
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<impl Iterator<Item = Result<UsbDevice, DeviceError>>, crate::Error> {
+    let mut enumerator = Enumerator::new().map_err(|e| os_error("Enumerator::new", e))?;
+
+    let devices = enumerator
+        .scan_devices()
+        .map_err(|e| os_error("Enumerator::scan_devices", e))?;
+
+    Ok(devices.filter_map(move |device| usb_device_from(&device, vid, pid).transpose()))
+}
+
+/// Reads a single device from its `DEVPATH` id without scanning every device
+pub fn find_by_id(id: &str) -> Option<UsbDevice> {
+    // Also accept ids that still have the `/devices/` prefix
+    let devpath = id.trim_start_matches('/');
+    let devpath = devpath.strip_prefix("devices/").unwrap_or(devpath);
+    let syspath = Path::new("/sys/devices").join(devpath);
+    let device = Device::from_syspath(&syspath).ok()?;
+
+    usb_device_from(&device, None, None).ok().flatten()
+}
 
-            let mut description = device
-                .property_value("ID_MODEL_FROM_DATABASE")
+/// Reads a udev device, returning `None` if it doesn't match the filters
+fn usb_device_from(
+    device: &Device,
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<Option<UsbDevice>, DeviceError> {
+    let syspath = device.syspath().to_string_lossy();
+
+    let usb_device = device_from_udev(
+        &syspath,
+        |name| device.property_value(name).and_then(|s| s.to_str()),
+        |name| device.attribute_value(name).and_then(|s| s.to_str()),
+        vid,
+        pid,
+    )?;
+
+    let mut usb_device = match usb_device {
+        Some(usb_device) => usb_device,
+        None => return Ok(None),
+    };
+
+    usb_device.device_path = device.syspath().to_str().map(|s| s.to_string());
+    usb_device.driver = device
+        .driver()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string());
+
+    // Root hubs have a PCI or platform device as their parent
+    usb_device.parent_id = device
+        .parent()
+        .filter(|parent| parent.subsystem() == Some(OsStr::new("usb")))
+        .and_then(|parent| {
+            parent
+                .property_value("DEVPATH")
                 .and_then(|s| s.to_str())
-                .map(|s| s.to_string());
+                .map(id_from_devpath)
+        });
+
+    usb_device.port_number = device.sysname().to_str().and_then(port_from_sysname);
+    usb_device.interfaces = interfaces_from_sysfs(device.syspath());
+    usb_device.raw_properties = raw_properties(device);
+
+    Ok(Some(usb_device))
+}
+
+/// Builds a device from its udev properties and sysfs attributes, returning
+/// `None` if it doesn't match the filters or isn't a USB device
+///
+/// The fields that need the device tree are left for the caller.
+fn device_from_udev<'a>(
+    syspath: &str,
+    property: impl Fn(&str) -> Option<&'a str>,
+    attribute: impl Fn(&str) -> Option<&'a str>,
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<Option<UsbDevice>, DeviceError> {
+    let error = |step, message: &dyn std::fmt::Display| DeviceError::new(syspath, step, message);
+
+    // Logs when a property the device needs is missing
+    let required = |name| {
+        let value = property(name);
+
+        if value.is_none() {
+            trace!("udev property {} missing for {:?}", name, syspath);
+        }
+
+        value
+    };
+
+    // Devices without IDs aren't USB devices
+    let vendor_id = match required("ID_VENDOR_ID") {
+        Some(id) => get_pid_or_vid(id).map_err(|e| error("ID_VENDOR_ID", &e))?,
+        None => return Ok(None),
+    };
+
+    if let Some(vid) = vid {
+        if vid != vendor_id {
+            return Ok(None);
+        }
+    }
+
+    let product_id = match required("ID_MODEL_ID") {
+        Some(id) => get_pid_or_vid(id).map_err(|e| error("ID_MODEL_ID", &e))?,
+        None => return Ok(None),
+    };
+
+    if let Some(pid) = pid {
+        if pid != product_id {
+            return Ok(None);
+        }
+    }
+
+    let id = required("DEVPATH")
+        .map(id_from_devpath)
+        .ok_or_else(|| error("DEVPATH", &"property missing"))?;
+
+    let description = property("ID_MODEL_FROM_DATABASE")
+        .and_then(clean_description)
+        .or_else(|| property("ID_MODEL").and_then(clean_description));
+
+    let manufacturer = property("ID_VENDOR_FROM_DATABASE")
+        .or_else(|| property("ID_VENDOR"))
+        .map(|s| s.to_string());
+
+    // Many hubs and composite devices have no serial number so it's
+    // optional rather than a reason to skip the device
+    let serial_number = serial_number_from(property("ID_SERIAL_SHORT"));
+
+    let hex = |name| attribute(name).and_then(|s| u8::from_str_radix(s.trim(), 16).ok());
+
+    let base_class = hex("bDeviceClass").map(DeviceBaseClass::from);
+    let sub_class = hex("bDeviceSubClass");
+    let protocol = hex("bDeviceProtocol");
 
-            if description.is_none() {
-                description = device
-                    .property_value("ID_MODEL")
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_string());
-            }
+    let speed = attribute("speed").and_then(speed_from_sysfs);
 
-            output.push(UsbDevice {
-                id,
-                vendor_id,
-                product_id,
-                description,
-            });
+    let bus_number = property("BUSNUM").and_then(|s| s.parse().ok());
+    let device_address = property("DEVNUM").and_then(|s| s.parse().ok());
 
-            Ok(())
-        }();
+    // Interfaces and their child devices report the interface they
+    // belong to
+    let interface_number = property("ID_USB_INTERFACE_NUM")
+        .or_else(|| attribute("bInterfaceNumber"))
+        .and_then(|s| u8::from_str_radix(s.trim(), 16).ok());
+
+    let device_version = attribute("bcdDevice")
+        .and_then(|s| u16::from_str_radix(s.trim(), 16).ok())
+        .map(version_from_bcd);
+
+    let usb_version = attribute("version").and_then(usb_version_from_sysfs);
+
+    let max_power_ma = attribute("bMaxPower").and_then(max_power_from_sysfs);
+
+    // Bit 6 of the active configuration's attributes is set when the device
+    // is self-powered
+    let self_powered = hex("bmAttributes").map(|attributes| attributes & 0x40 != 0);
+
+    let num_configurations = attribute("bNumConfigurations").and_then(|s| s.trim().parse().ok());
+
+    let active_configuration = attribute("bConfigurationValue").and_then(configuration_from_sysfs);
+
+    let removable = attribute("removable").and_then(removable_from_sysfs);
+
+    Ok(Some(UsbDevice {
+        id,
+        vendor_id,
+        product_id,
+        description,
+        product_name: None,
+        manufacturer,
+        serial_number,
+        base_class,
+        sub_class,
+        protocol,
+        speed,
+        bus_number,
+        device_address,
+        interface_number,
+        device_version,
+        usb_version,
+        device_path: None,
+        max_power_ma,
+        self_powered,
+        driver: None,
+        parent_id: None,
+        port_number: None,
+        num_configurations,
+        interfaces: Vec::new(),
+        location_id: None,
+        removable,
+        present: true,
+        hardware_ids: Vec::new(),
+        raw_properties: None,
+        active_configuration,
+    }))
+}
+
+/// Parses `bConfigurationValue`, which is empty while the device isn't
+/// configured
+fn configuration_from_sysfs(value: &str) -> Option<u8> {
+    match value.trim() {
+        "" => Some(0),
+        value => value.parse().ok(),
+    }
+}
+
+/// Every USB `DEVPATH` starts with `/devices/`, so that is left out of the id
+fn id_from_devpath(devpath: &str) -> String {
+    devpath
+        .strip_prefix("/devices/")
+        .unwrap_or(devpath)
+        .to_string()
+}
+
+/// Reads every udev property when the `raw-properties` feature is enabled
+fn raw_properties(device: &Device) -> Option<HashMap<String, String>> {
+    if !cfg!(feature = "raw-properties") {
+        return None;
+    }
+
+    Some(
+        device
+            .properties()
+            .map(|property| {
+                (
+                    property.name().to_string_lossy().into_owned(),
+                    property.value().to_string_lossy().into_owned(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Reads the number of downstream ports of a hub from sysfs `maxchild`
+pub fn hub_port_count(device: &UsbDevice) -> Option<u8> {
+    let path = Path::new(device.device_path.as_ref()?).join("maxchild");
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Checks that udev has the IDs of USB devices and that their device nodes
+/// can be opened
+pub fn check_permissions() -> Result<(), PermissionError> {
+    let error = |call, e| PermissionError::Enumeration(os_error(call, e));
+
+    let mut enumerator = Enumerator::new().map_err(|e| error("Enumerator::new", e))?;
+    enumerator
+        .match_subsystem("usb")
+        .map_err(|e| error("Enumerator::match_subsystem", e))?;
+    enumerator
+        .match_property("DEVTYPE", "usb_device")
+        .map_err(|e| error("Enumerator::match_property", e))?;
+
+    let devices: Vec<Device> = enumerator
+        .scan_devices()
+        .map_err(|e| error("Enumerator::scan_devices", e))?
+        .collect();
+
+    let missing = devices
+        .iter()
+        .filter(|device| device.property_value("ID_VENDOR_ID").is_none())
+        .count();
+
+    if missing > 0 && missing == devices.len() {
+        return Err(PermissionError::MissingProperties { devices: missing });
+    }
+
+    // Hub nodes are usually only writable by root, which is fine since
+    // they're rarely opened directly
+    let denied: Vec<String> = devices
+        .iter()
+        .filter(|device| device.attribute_value("bDeviceClass") != Some(OsStr::new("09")))
+        .filter_map(|device| device.devnode())
+        .filter(|node| {
+            matches!(
+                OpenOptions::new().read(true).write(true).open(node),
+                Err(e) if e.kind() == ErrorKind::PermissionDenied
+            )
+        })
+        .map(|node| node.display().to_string())
+        .collect();
+
+    if denied.is_empty() {
+        Ok(())
+    } else {
+        Err(PermissionError::DeviceNodes(denied))
     }
+}
 
-    output
+/// Listens for udev USB device events
+pub struct HotplugMonitor {
+    socket: MonitorSocket,
+}
+
+impl HotplugMonitor {
+    /// Returns `None` if the udev monitor could not be created
+    pub fn new() -> Option<Self> {
+        let socket = MonitorBuilder::new()
+            .and_then(|builder| builder.match_subsystem("usb"))
+            .and_then(|builder| builder.listen())
+            .ok()?;
+
+        Some(HotplugMonitor { socket })
+    }
+
+    /// Waits up to `timeout` for device events, returning `true` if any were
+    /// received
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        let mut fds = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let ready = unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            return false;
+        }
+
+        // Drain all pending events so a burst only causes one re-enumeration
+        self.socket.by_ref().count() > 0
+    }
+}
+
+fn os_error(call: &'static str, error: std::io::Error) -> EnumerationError {
+    EnumerationError::new(call, error.raw_os_error().unwrap_or_default())
+}
+
+fn serial_number_from(value: Option<&str>) -> Option<String> {
+    value.filter(|s| !s.is_empty()).map(|s| s.to_string())
+}
+
+/// Parses the sysfs `speed` attribute, which is in Mbit/s
+fn speed_from_sysfs(speed: &str) -> Option<UsbSpeed> {
+    let speed = speed.trim();
+    if speed.is_empty() {
+        return None;
+    }
+
+    Some(match speed {
+        "1.5" => UsbSpeed::Low,
+        "12" => UsbSpeed::Full,
+        "480" => UsbSpeed::High,
+        "5000" => UsbSpeed::Super,
+        _ => match speed.parse::<u32>() {
+            Ok(mbps) if mbps >= 10000 => UsbSpeed::SuperPlus,
+            _ => UsbSpeed::Unknown,
+        },
+    })
+}
+
+/// Parses the sysfs `version` attribute, which is `bcdUSB` formatted like
+/// ` 2.10`
+fn usb_version_from_sysfs(version: &str) -> Option<(u8, u8)> {
+    let (major, minor) = version.trim().split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Parses the sysfs `bMaxPower` attribute, which the kernel has already
+/// scaled to mA, like `500mA`
+fn max_power_from_sysfs(max_power: &str) -> Option<u16> {
+    max_power.trim().strip_suffix("mA")?.parse().ok()
+}
+
+/// Parses the sysfs `removable` attribute, which is `unknown` when the hub
+/// doesn't describe its ports
+fn removable_from_sysfs(removable: &str) -> Option<bool> {
+    match removable.trim() {
+        "removable" => Some(true),
+        "fixed" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses the port from a sysfs name like `1-1.3`, where the last number is
+/// the port on the parent hub. Interfaces like `1-1.3:1.0` report the port
+/// of their device and root hubs like `usb1` have no port.
+fn port_from_sysname(name: &str) -> Option<u8> {
+    let device = name.split(':').next()?;
+    let (_, port) = device.rsplit_once(['.', '-'])?;
+    port.parse().ok()
+}
+
+/// Reads the interfaces of the active configuration from the interface
+/// directories like `1-1.3:1.0` in a device's sysfs directory
+fn interfaces_from_sysfs(path: &Path) -> Vec<UsbInterface> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    // Only interface directories have a `bInterfaceNumber`
+    let mut interfaces: Vec<UsbInterface> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| interface_from_sysfs(&entry.path()))
+        .collect();
+
+    interfaces.sort_by_key(|interface| interface.number);
+    interfaces
+}
+
+fn interface_from_sysfs(path: &Path) -> Option<UsbInterface> {
+    let read = |name| fs::read_to_string(path.join(name)).ok();
+    let hex = |name| u8::from_str_radix(read(name)?.trim(), 16).ok();
+
+    Some(UsbInterface {
+        number: hex("bInterfaceNumber")?,
+        base_class: DeviceBaseClass::from(hex("bInterfaceClass")?),
+        sub_class: hex("bInterfaceSubClass")?,
+        protocol: hex("bInterfaceProtocol")?,
+        description: read("interface").as_deref().and_then(clean_description),
+    })
 }
 
 fn get_pid_or_vid(id: &str) -> Result<u16, Box<dyn Error>> {
@@ -78,5 +437,185 @@ fn get_pid_or_vid(id: &str) -> Result<u16, Box<dyn Error>> {
         id = &id[2..];
     }
 
-    Ok(u16::from_str_radix(&id, 16)?)
+    Ok(u16::from_str_radix(id, 16)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_from_sysfs() {
+        assert_eq!(speed_from_sysfs("1.5"), Some(UsbSpeed::Low));
+        assert_eq!(speed_from_sysfs("12"), Some(UsbSpeed::Full));
+        assert_eq!(speed_from_sysfs("480\n"), Some(UsbSpeed::High));
+        assert_eq!(speed_from_sysfs("5000"), Some(UsbSpeed::Super));
+        assert_eq!(speed_from_sysfs("10000"), Some(UsbSpeed::SuperPlus));
+        assert_eq!(speed_from_sysfs("20000"), Some(UsbSpeed::SuperPlus));
+        assert_eq!(speed_from_sysfs("42"), Some(UsbSpeed::Unknown));
+        assert_eq!(speed_from_sysfs(""), None);
+    }
+
+    #[test]
+    fn test_usb_version_from_sysfs() {
+        assert_eq!(usb_version_from_sysfs(" 2.10\n"), Some((2, 10)));
+        assert_eq!(usb_version_from_sysfs(" 3.00"), Some((3, 0)));
+        assert_eq!(usb_version_from_sysfs(""), None);
+    }
+
+    #[test]
+    fn test_max_power_from_sysfs() {
+        assert_eq!(max_power_from_sysfs("500mA\n"), Some(500));
+        assert_eq!(max_power_from_sysfs("0mA"), Some(0));
+        assert_eq!(max_power_from_sysfs(""), None);
+    }
+
+    #[test]
+    fn test_removable_from_sysfs() {
+        assert_eq!(removable_from_sysfs("removable\n"), Some(true));
+        assert_eq!(removable_from_sysfs("fixed\n"), Some(false));
+        assert_eq!(removable_from_sysfs("unknown\n"), None);
+        assert_eq!(removable_from_sysfs(""), None);
+    }
+
+    #[test]
+    fn test_configuration_from_sysfs() {
+        assert_eq!(configuration_from_sysfs("1\n"), Some(1));
+        assert_eq!(configuration_from_sysfs("\n"), Some(0));
+        assert_eq!(configuration_from_sysfs("x"), None);
+    }
+
+    #[test]
+    fn test_id_from_devpath() {
+        assert_eq!(
+            id_from_devpath("/devices/pci0000:00/0000:00:14.0/usb1/1-1/1-1.3"),
+            "pci0000:00/0000:00:14.0/usb1/1-1/1-1.3"
+        );
+        assert_eq!(id_from_devpath("/other/usb1"), "/other/usb1");
+    }
+
+    #[test]
+    fn test_port_from_sysname() {
+        assert_eq!(port_from_sysname("1-1.3"), Some(3));
+        assert_eq!(port_from_sysname("2-4"), Some(4));
+        assert_eq!(port_from_sysname("1-1.3:1.0"), Some(3));
+        assert_eq!(port_from_sysname("usb1"), None);
+    }
+
+    #[test]
+    fn test_get_pid_or_vid() {
+        assert_eq!(get_pid_or_vid("046d").unwrap(), 0x046d);
+        assert_eq!(get_pid_or_vid("0x046d").unwrap(), 0x046d);
+        assert_eq!(get_pid_or_vid("C52B").unwrap(), 0xc52b);
+        assert!(get_pid_or_vid("xyz").is_err());
+        assert!(get_pid_or_vid("").is_err());
+    }
+
+    #[test]
+    fn test_device_from_udev() {
+        let device = |properties: &[(&'static str, &'static str)],
+                      attributes: &[(&'static str, &'static str)],
+                      vid: Option<u16>| {
+            let properties: HashMap<_, _> = properties.iter().copied().collect();
+            let attributes: HashMap<_, _> = attributes.iter().copied().collect();
+
+            device_from_udev(
+                "/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2",
+                |name| properties.get(name).copied(),
+                |name| attributes.get(name).copied(),
+                vid,
+                None,
+            )
+        };
+
+        let properties = [
+            ("ID_VENDOR_ID", "046d"),
+            ("ID_MODEL_ID", "0xc52b"),
+            ("DEVPATH", "/devices/pci0000:00/0000:00:14.0/usb1/1-2"),
+            ("ID_MODEL", "USB_Receiver"),
+            ("ID_VENDOR", "Logitech"),
+            ("BUSNUM", "001"),
+            ("DEVNUM", "004"),
+        ];
+        let attributes = [("bDeviceClass", "42"), ("bcdDevice", "1201")];
+
+        let usb_device = device(&properties, &attributes, None).unwrap().unwrap();
+        assert_eq!(usb_device.id, "pci0000:00/0000:00:14.0/usb1/1-2");
+        assert_eq!(usb_device.vendor_id, 0x046d);
+        assert_eq!(usb_device.product_id, 0xc52b);
+        assert_eq!(usb_device.description.as_deref(), Some("USB_Receiver"));
+        assert_eq!(usb_device.manufacturer.as_deref(), Some("Logitech"));
+        assert_eq!(usb_device.serial_number, None);
+        assert_eq!(usb_device.base_class, Some(DeviceBaseClass::Unknown(0x42)));
+        assert_eq!(usb_device.device_version, Some((12, 1)));
+        assert_eq!(usb_device.bus_number, Some(1));
+        assert_eq!(usb_device.device_address, Some(4));
+
+        let with_serial = [&properties[..], &[("ID_SERIAL_SHORT", "0000000004BE")]].concat();
+        let usb_device = device(&with_serial, &attributes, None).unwrap().unwrap();
+        assert_eq!(usb_device.serial_number.as_deref(), Some("0000000004BE"));
+
+        // Devices without IDs aren't USB devices
+        assert_eq!(device(&properties[2..], &attributes, None), Ok(None));
+
+        let invalid = [("ID_VENDOR_ID", "04xd"), ("ID_MODEL_ID", "c52b")];
+        let error = device(&invalid, &attributes, None).unwrap_err();
+        assert_eq!(error.step, "ID_VENDOR_ID");
+
+        assert_eq!(device(&properties, &attributes, Some(0x1234)), Ok(None));
+    }
+
+    #[test]
+    fn test_serial_number_optional() {
+        assert_eq!(serial_number_from(None), None);
+        assert_eq!(serial_number_from(Some("")), None);
+        assert_eq!(
+            serial_number_from(Some("0000000004BE")),
+            Some("0000000004BE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_interfaces_from_sysfs() {
+        let device = std::env::temp_dir().join(format!("usb_enumeration_{}", std::process::id()));
+        let write = |dir: &str, name: &str, value: &str| {
+            fs::create_dir_all(device.join(dir)).unwrap();
+            fs::write(device.join(dir).join(name), value).unwrap();
+        };
+
+        write("1-1:1.1", "bInterfaceNumber", "01\n");
+        write("1-1:1.1", "bInterfaceClass", "08\n");
+        write("1-1:1.1", "bInterfaceSubClass", "06\n");
+        write("1-1:1.1", "bInterfaceProtocol", "50\n");
+        write("1-1:1.0", "bInterfaceNumber", "00\n");
+        write("1-1:1.0", "bInterfaceClass", "03\n");
+        write("1-1:1.0", "bInterfaceSubClass", "01\n");
+        write("1-1:1.0", "bInterfaceProtocol", "02\n");
+        write("1-1:1.0", "interface", "Mouse\n");
+        write("power", "control", "auto\n");
+
+        let interfaces = interfaces_from_sysfs(&device);
+        fs::remove_dir_all(&device).unwrap();
+
+        assert_eq!(
+            interfaces,
+            vec![
+                UsbInterface {
+                    number: 0,
+                    base_class: DeviceBaseClass::HumanInterfaceDevice,
+                    sub_class: 0x01,
+                    protocol: 0x02,
+                    description: Some("Mouse".to_string()),
+                },
+                UsbInterface {
+                    number: 1,
+                    base_class: DeviceBaseClass::MassStorage,
+                    sub_class: 0x06,
+                    protocol: 0x50,
+                    description: None,
+                },
+            ]
+        );
+        assert!(interfaces_from_sysfs(Path::new("/nonexistent")).is_empty());
+    }
 }
@@ -1,97 +1,759 @@
 use crate::common::*;
-use core_foundation::{base::*, dictionary::*, number::*, string::*};
-use io_kit_sys::{types::*, usb::lib::*, *};
+use core_foundation::{base::*, dictionary::*, number::*, runloop::*, string::*};
+use io_kit_sys::{keys::*, types::*, usb::lib::*, *};
 use mach::kern_return::*;
-use std::{error::Error, mem::MaybeUninit};
-
-pub fn enumerate_platform(vid: Option<u16>, pid: Option<u16>) -> Vec<UsbDevice> {
-    let mut output = Vec::new();
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ffi::CStr,
+    os::raw::{c_char, c_void},
+    ptr::null_mut,
+    time::Duration,
+};
 
+pub fn enumerate_platform(vid: Option<u16>, pid: Option<u16>) -> Result<DeviceIter, crate::Error> {
     unsafe {
         let matching_dict = IOServiceMatching(kIOUSBDeviceClassName);
         if matching_dict.as_ref().is_none() {
-            panic!("Failed to get IOServiceMatching");
+            return Err(EnumerationError::new("IOServiceMatching", KERN_FAILURE).into());
         }
 
         let mut iter: io_iterator_t = 0;
 
         let kr = IOServiceGetMatchingServices(kIOMasterPortDefault, matching_dict, &mut iter);
         if kr != KERN_SUCCESS {
-            panic!("Failed IOServiceGetMatchingServices");
+            return Err(EnumerationError::new("IOServiceGetMatchingServices", kr).into());
         }
 
-        #[allow(unused_assignments)]
-        let mut device: io_service_t = 0;
+        Ok(DeviceIter { iter, vid, pid })
+    }
+}
+
+/// Lazily reads each device from an IOKit service iterator
+pub struct DeviceIter {
+    iter: io_iterator_t,
+    vid: Option<u16>,
+    pid: Option<u16>,
+}
 
-        #[allow(clippy::unit_cmp)]
-        while (device = IOIteratorNext(iter)) == () && device > 0 {
-            #[allow(clippy::uninit_assumed_init)]
-            let mut props: CFMutableDictionaryRef = MaybeUninit::uninit().assume_init();
+impl Iterator for DeviceIter {
+    type Item = Result<UsbDevice, DeviceError>;
 
-            let _result =
-                IORegistryEntryCreateCFProperties(device, &mut props, kCFAllocatorDefault, 0);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // `IOIteratorNext` returns `IO_OBJECT_NULL` once there are no
+            // more objects. It's `MACH_PORT_NULL` so is never a valid service
+            let device = unsafe { IOIteratorNext(self.iter) };
+            if device == IO_OBJECT_NULL {
+                return None;
+            }
 
-            let properties: CFDictionary<CFString, CFType> =
-                CFMutableDictionary::wrap_under_get_rule(props).to_immutable();
-
-            let _ = || -> Result<(), Box<dyn Error>> {
-                let key = CFString::from_static_string("idVendor");
-                let vendor_id = properties
-                    .find(&key)
-                    .and_then(|value_ref| value_ref.downcast::<CFNumber>())
-                    .ok_or(ParseError)?
-                    .to_i32()
-                    .ok_or(ParseError)? as u16;
-
-                if let Some(vid) = vid {
-                    if vid != vendor_id {
-                        return Ok(());
-                    }
+            let (result, device_path, driver, parent_id, interfaces) = unsafe {
+                let device_path = registry_path(device);
+
+                // Identify devices in errors by their registry path or
+                // otherwise their handle
+                let error_id = device_path.clone().unwrap_or_else(|| device.to_string());
+
+                // This fails if the device is unplugged during the scan
+                let mut props: CFMutableDictionaryRef = null_mut();
+                let kr =
+                    IORegistryEntryCreateCFProperties(device, &mut props, kCFAllocatorDefault, 0);
+
+                if kr != KERN_SUCCESS || props.is_null() {
+                    IOObjectRelease(device);
+                    return Some(Err(DeviceError::new(
+                        error_id,
+                        "IORegistryEntryCreateCFProperties",
+                        format!("failed with error code {}", kr),
+                    )));
                 }
 
-                let key = CFString::from_static_string("idProduct");
-                let product_id = properties
-                    .find(&key)
-                    .and_then(|value_ref| value_ref.downcast::<CFNumber>())
-                    .ok_or(ParseError)?
-                    .to_i32()
-                    .ok_or(ParseError)? as u16;
-
-                if let Some(pid) = pid {
-                    if pid != product_id {
-                        return Ok(());
-                    }
+                let properties: CFDictionary<CFString, CFType> =
+                    CFMutableDictionary::wrap_under_create_rule(props).to_immutable();
+
+                let driver = client_class(device);
+                let parent_id = parent_id(device);
+                let interfaces = interfaces(device);
+
+                IOObjectRelease(device);
+
+                (
+                    device_from_properties(&properties, &error_id, self.vid, self.pid),
+                    device_path,
+                    driver,
+                    parent_id,
+                    interfaces,
+                )
+            };
+
+            match result {
+                Ok(Some(mut usb_device)) => {
+                    usb_device.device_path = device_path;
+                    usb_device.driver = driver;
+                    usb_device.parent_id = parent_id;
+                    usb_device.interfaces = interfaces;
+                    return Some(Ok(usb_device));
                 }
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl Drop for DeviceIter {
+    fn drop(&mut self) {
+        unsafe { IOObjectRelease(self.iter) };
+    }
+}
+
+/// Reads a device's registry properties, returning `None` if it doesn't
+/// match the filters
+fn device_from_properties(
+    properties: &CFDictionary<CFString, CFType>,
+    error_id: &str,
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<Option<UsbDevice>, DeviceError> {
+    let error = |step| DeviceError::new(error_id, step, "property missing or invalid");
+
+    let key = CFString::from_static_string("idVendor");
+    let vendor_id = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .ok_or_else(|| error("idVendor"))?
+        .to_i32()
+        .ok_or_else(|| error("idVendor"))? as u16;
 
-                let key = CFString::from_static_string("sessionID");
-                let id = properties
-                    .find(&key)
-                    .and_then(|value_ref| value_ref.downcast::<CFNumber>())
-                    .ok_or(ParseError)?
-                    .to_i64()
-                    .ok_or(ParseError)?;
-
-                let key = CFString::from_static_string("USB Product Name");
-                let description = properties
-                    .find(&key)
-                    .and_then(|value_ref| value_ref.downcast::<CFString>())
-                    .map(|s| s.to_string());
-
-                output.push(UsbDevice {
-                    id: id.to_string(),
-                    vendor_id,
-                    product_id,
-                    description,
-                });
-
-                Ok(())
-            }();
-
-            IOObjectRelease(device);
+    if let Some(vid) = vid {
+        if vid != vendor_id {
+            return Ok(None);
         }
+    }
+
+    let key = CFString::from_static_string("idProduct");
+    let product_id = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .ok_or_else(|| error("idProduct"))?
+        .to_i32()
+        .ok_or_else(|| error("idProduct"))? as u16;
 
-        IOObjectRelease(iter);
+    if let Some(pid) = pid {
+        if pid != product_id {
+            return Ok(None);
+        }
     }
 
-    output
+    let id = device_id(properties).ok_or_else(|| error("locationID"))?;
+
+    let key = CFString::from_static_string("USB Product Name");
+    let description = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFString>())
+        .and_then(|s| clean_description(&s.to_string()));
+
+    let key = CFString::from_static_string("USB Vendor Name");
+    let manufacturer = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFString>())
+        .map(|s| s.to_string());
+
+    let key = CFString::from_static_string("USB Serial Number");
+    let serial_number = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFString>())
+        .map(|s| s.to_string());
+
+    let key = CFString::from_static_string("bDeviceClass");
+    let base_class = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|c| DeviceBaseClass::from(c as u8));
+
+    let key = CFString::from_static_string("bDeviceSubClass");
+    let sub_class = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|c| c as u8);
+
+    let key = CFString::from_static_string("bDeviceProtocol");
+    let protocol = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|c| c as u8);
+
+    let key = CFString::from_static_string("Device Speed");
+    let speed = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(speed_from_device_speed);
+
+    let key = CFString::from_static_string("locationID");
+    let location_id = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i64())
+        .map(|location| location as u32);
+
+    // The bus number is the top byte of the location ID
+    let bus_number = location_id.map(|location| (location >> 24) as u8);
+
+    let key = CFString::from_static_string("USB Address");
+    let device_address = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|address| address as u8);
+
+    let key = CFString::from_static_string("bcdDevice");
+    let device_version = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|bcd| version_from_bcd(bcd as u16));
+
+    let key = CFString::from_static_string("bcdUSB");
+    let usb_version = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|bcd| version_from_bcd(bcd as u16));
+
+    // This is the raw `bMaxPower` in units of 2mA
+    let key = CFString::from_static_string("Requested Power");
+    let max_power_ma = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|power| power as u16 * 2);
+
+    let key = CFString::from_static_string("PortNum");
+    let port_number = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|port| port as u8)
+        .or_else(|| location_id.and_then(port_from_location_id));
+
+    // `kUSBDeviceNumConfigs`
+    let key = CFString::from_static_string("bNumConfigurations");
+    let num_configurations = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        .and_then(|n| n.to_i32())
+        .map(|count| count as u8);
+
+    // Older versions of macOS use `USB Current Configuration`
+    let active_configuration = ["kUSBCurrentConfiguration", "USB Current Configuration"]
+        .iter()
+        .find_map(|&key| {
+            properties
+                .find(CFString::from_static_string(key))
+                .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+        })
+        .and_then(|n| n.to_i32())
+        .map(|value| value as u8);
+
+    // Set from the hub's port descriptions as `yes` or `no`
+    let key = CFString::from_static_string("non-removable");
+    let removable = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFString>())
+        .and_then(|s| match s.to_string().as_str() {
+            "yes" => Some(false),
+            "no" => Some(true),
+            _ => None,
+        });
+
+    Ok(Some(UsbDevice {
+        id,
+        vendor_id,
+        product_id,
+        description,
+        product_name: None,
+        manufacturer,
+        serial_number,
+        base_class,
+        sub_class,
+        protocol,
+        speed,
+        bus_number,
+        device_address,
+        interface_number: None,
+        device_version,
+        usb_version,
+        device_path: None,
+        max_power_ma,
+        self_powered: None,
+        driver: None,
+        parent_id: None,
+        port_number,
+        num_configurations,
+        interfaces: Vec::new(),
+        location_id,
+        removable,
+        present: true,
+        hardware_ids: Vec::new(),
+        raw_properties: raw_properties(properties),
+        active_configuration,
+    }))
+}
+
+/// Builds the `id` from the `locationID` and the vendor and product IDs, which
+/// unlike the `sessionID` stay the same when the device is reconnected to the
+/// same port
+///
+/// Devices without a `locationID` fall back to their `sessionID`.
+fn device_id(properties: &CFDictionary<CFString, CFType>) -> Option<String> {
+    let number = |key: &'static str| {
+        properties
+            .find(CFString::from_static_string(key))
+            .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+            .and_then(|n| n.to_i64())
+    };
+
+    match (
+        number("locationID"),
+        number("idVendor"),
+        number("idProduct"),
+    ) {
+        (Some(location_id), Some(vendor_id), Some(product_id)) => Some(format!(
+            "{:08x}:{:04x}:{:04x}",
+            location_id as u32, vendor_id as u16, product_id as u16
+        )),
+        _ => number("sessionID").map(|id| id.to_string()),
+    }
+}
+
+/// Hubs only report their number of ports when opened
+pub fn hub_port_count(_device: &UsbDevice) -> Option<u8> {
+    None
+}
+
+/// Reads every registry property when the `raw-properties` feature is
+/// enabled, using the CoreFoundation description for values that aren't
+/// strings or numbers
+fn raw_properties(properties: &CFDictionary<CFString, CFType>) -> Option<HashMap<String, String>> {
+    if !cfg!(feature = "raw-properties") {
+        return None;
+    }
+
+    let (keys, values) = properties.get_keys_and_values();
+
+    Some(
+        keys.into_iter()
+            .zip(values)
+            .map(|(key, value)| {
+                let key = unsafe { CFString::wrap_under_get_rule(key as CFStringRef) };
+                let value = unsafe { CFType::wrap_under_get_rule(value as CFTypeRef) };
+
+                let value = if let Some(s) = value.downcast::<CFString>() {
+                    s.to_string()
+                } else if let Some(n) = value.downcast::<CFNumber>() {
+                    n.to_i64()
+                        .map(|n| n.to_string())
+                        .unwrap_or_else(|| format!("{:?}", n))
+                } else {
+                    format!("{:?}", value)
+                };
+
+                (key.to_string(), value)
+            })
+            .collect(),
+    )
+}
+
+/// Reads the IORegistry path of a device in the service plane
+unsafe fn registry_path(device: io_service_t) -> Option<String> {
+    // Paths are returned in an `io_string_t`, which is 512 bytes
+    let mut path: [c_char; 512] = [0; 512];
+
+    let kr = IORegistryEntryGetPath(device, kIOServicePlane as *mut c_char, path.as_mut_ptr());
+    if kr != KERN_SUCCESS {
+        return None;
+    }
+
+    CStr::from_ptr(path.as_ptr())
+        .to_str()
+        .ok()
+        .map(|s| s.to_string())
+}
+
+/// Reads the IOKit class of the first client of a device in the service plane
+unsafe fn client_class(device: io_service_t) -> Option<String> {
+    let mut child: io_registry_entry_t = 0;
+
+    let kr = IORegistryEntryGetChildEntry(device, kIOServicePlane as *mut c_char, &mut child);
+    if kr != KERN_SUCCESS {
+        return None;
+    }
+
+    let class = IOObjectCopyClass(child);
+    IOObjectRelease(child);
+
+    if class.is_null() {
+        return None;
+    }
+
+    Some(CFString::wrap_under_create_rule(class).to_string())
+}
+
+/// Reads the interfaces of a device from its interface nubs, which are its
+/// children in the service plane
+unsafe fn interfaces(device: io_service_t) -> Vec<UsbInterface> {
+    let mut iter: io_iterator_t = 0;
+
+    let kr = IORegistryEntryGetChildIterator(device, kIOServicePlane as *mut c_char, &mut iter);
+    if kr != KERN_SUCCESS {
+        return Vec::new();
+    }
+
+    let mut interfaces = Vec::new();
+
+    loop {
+        let child = IOIteratorNext(iter);
+        if child == IO_OBJECT_NULL {
+            break;
+        }
+
+        let mut props: CFMutableDictionaryRef = null_mut();
+        let kr = IORegistryEntryCreateCFProperties(child, &mut props, kCFAllocatorDefault, 0);
+        IOObjectRelease(child);
+
+        if kr != KERN_SUCCESS {
+            continue;
+        }
+
+        let properties: CFDictionary<CFString, CFType> =
+            CFMutableDictionary::wrap_under_create_rule(props).to_immutable();
+
+        if let Some(interface) = interface_from(&properties) {
+            interfaces.push(interface);
+        }
+    }
+
+    IOObjectRelease(iter);
+
+    interfaces.sort_by_key(|interface| interface.number);
+    interfaces
+}
+
+/// Reads an interface nub's properties, returning `None` for children that
+/// aren't interfaces
+fn interface_from(properties: &CFDictionary<CFString, CFType>) -> Option<UsbInterface> {
+    let number = |name| {
+        let key = CFString::from_static_string(name);
+        properties
+            .find(&key)
+            .and_then(|value_ref| value_ref.downcast::<CFNumber>())
+            .and_then(|n| n.to_i32())
+            .map(|n| n as u8)
+    };
+
+    let key = CFString::from_static_string("USB Interface Name");
+    let description = properties
+        .find(&key)
+        .and_then(|value_ref| value_ref.downcast::<CFString>())
+        .and_then(|s| clean_description(&s.to_string()));
+
+    Some(UsbInterface {
+        number: number("bInterfaceNumber")?,
+        base_class: DeviceBaseClass::from(number("bInterfaceClass")?),
+        sub_class: number("bInterfaceSubClass")?,
+        protocol: number("bInterfaceProtocol")?,
+        description,
+    })
+}
+
+/// Finds the `id` of the nearest USB device above a device in the service
+/// plane, which is the hub it's connected to
+unsafe fn parent_id(device: io_service_t) -> Option<String> {
+    let key = CFString::from_static_string("sessionID");
+    let mut entry = device;
+
+    // Hubs are usually a few levels up, with ports and drivers in between
+    for _ in 0..16 {
+        let mut parent: io_registry_entry_t = 0;
+        let kr = IORegistryEntryGetParentEntry(entry, kIOServicePlane as *mut c_char, &mut parent);
+
+        if entry != device {
+            IOObjectRelease(entry);
+        }
+
+        if kr != KERN_SUCCESS {
+            return None;
+        }
+
+        entry = parent;
+
+        // Only USB devices have a session ID
+        let value = IORegistryEntryCreateCFProperty(
+            entry,
+            key.as_concrete_TypeRef(),
+            kCFAllocatorDefault,
+            0,
+        );
+
+        if !value.is_null() {
+            CFRelease(value);
+
+            let mut props: CFMutableDictionaryRef = null_mut();
+            let kr = IORegistryEntryCreateCFProperties(entry, &mut props, kCFAllocatorDefault, 0);
+            IOObjectRelease(entry);
+
+            if kr != KERN_SUCCESS {
+                return None;
+            }
+
+            let properties: CFDictionary<CFString, CFType> =
+                CFMutableDictionary::wrap_under_create_rule(props).to_immutable();
+
+            return device_id(&properties);
+        }
+    }
+
+    IOObjectRelease(entry);
+    None
+}
+
+/// Finds the port from a location ID like `0x14320000`, where the top byte is
+/// the bus and each following nibble is the port at the next hub tier
+fn port_from_location_id(location: u32) -> Option<u8> {
+    (0..6)
+        .map(|tier| ((location >> (20 - tier * 4)) & 0xf) as u8)
+        .take_while(|&port| port != 0)
+        .last()
+}
+
+/// Maps the IOKit `Device Speed` property (`kUSBDeviceSpeed*`)
+fn speed_from_device_speed(speed: i32) -> UsbSpeed {
+    match speed {
+        0 => UsbSpeed::Low,
+        1 => UsbSpeed::Full,
+        2 => UsbSpeed::High,
+        3 => UsbSpeed::Super,
+        4 | 5 => UsbSpeed::SuperPlus,
+        _ => UsbSpeed::Unknown,
+    }
+}
+
+thread_local! {
+    // Set by the notification callback when a device is matched or
+    // terminated. Callbacks run on the thread that runs the run loop so no
+    // synchronisation is needed
+    static DEVICE_CHANGED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Listens for IOKit USB device matched and terminated notifications
+pub struct HotplugMonitor {
+    port: IONotificationPortRef,
+    source: CFRunLoopSource,
+    iterators: Vec<io_iterator_t>,
+}
+
+impl HotplugMonitor {
+    /// Returns `None` if the notifications could not be registered
+    pub fn new() -> Option<Self> {
+        unsafe {
+            let port = IONotificationPortCreate(kIOMasterPortDefault);
+            if port.is_null() {
+                return None;
+            }
+
+            let source =
+                CFRunLoopSource::wrap_under_get_rule(IONotificationPortGetRunLoopSource(port));
+            CFRunLoop::get_current().add_source(&source, kCFRunLoopDefaultMode);
+
+            let mut monitor = HotplugMonitor {
+                port,
+                source,
+                iterators: Vec::new(),
+            };
+
+            for notification in &[kIOMatchedNotification, kIOTerminatedNotification] {
+                let mut iter: io_iterator_t = 0;
+
+                let kr = IOServiceAddMatchingNotification(
+                    port,
+                    *notification as *mut _,
+                    IOServiceMatching(kIOUSBDeviceClassName) as _,
+                    device_notification,
+                    null_mut(),
+                    &mut iter,
+                );
+                if kr != KERN_SUCCESS {
+                    return None;
+                }
+
+                // The iterator must be drained to arm the notification
+                drain_iterator(iter);
+                monitor.iterators.push(iter);
+            }
+
+            Some(monitor)
+        }
+    }
+
+    /// Runs the run loop for up to `timeout`, returning `true` if any device
+    /// notifications were received
+    ///
+    /// The run loop is run in bounded slices rather than until another
+    /// thread calls `CFRunLoopStop`, because closing is signalled by the
+    /// subscription's close channel like on the other platforms, and a
+    /// channel can't wake a run loop. Stopping it directly would need every
+    /// handle that can close the thread to hold its `CFRunLoop`. The caller
+    /// checks the channel between slices, see `CLOSE_CHECK_INTERVAL`.
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        unsafe {
+            CFRunLoopRunInMode(kCFRunLoopDefaultMode, timeout.as_secs_f64(), 1);
+        }
+
+        DEVICE_CHANGED.with(|changed| changed.replace(false))
+    }
+}
+
+impl Drop for HotplugMonitor {
+    fn drop(&mut self) {
+        CFRunLoop::get_current().remove_source(&self.source, unsafe { kCFRunLoopDefaultMode });
+
+        unsafe {
+            for iter in &self.iterators {
+                IOObjectRelease(*iter);
+            }
+
+            IONotificationPortDestroy(self.port);
+        }
+    }
+}
+
+unsafe extern "C" fn device_notification(_refcon: *mut c_void, iterator: io_iterator_t) {
+    drain_iterator(iterator);
+    DEVICE_CHANGED.with(|changed| changed.set(true));
+}
+
+unsafe fn drain_iterator(iter: io_iterator_t) {
+    loop {
+        let device = IOIteratorNext(iter);
+        if device == IO_OBJECT_NULL {
+            break;
+        }
+
+        IOObjectRelease(device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_from_device_speed() {
+        assert_eq!(speed_from_device_speed(0), UsbSpeed::Low);
+        assert_eq!(speed_from_device_speed(1), UsbSpeed::Full);
+        assert_eq!(speed_from_device_speed(2), UsbSpeed::High);
+        assert_eq!(speed_from_device_speed(3), UsbSpeed::Super);
+        assert_eq!(speed_from_device_speed(4), UsbSpeed::SuperPlus);
+        assert_eq!(speed_from_device_speed(42), UsbSpeed::Unknown);
+    }
+
+    #[test]
+    fn test_port_from_location_id() {
+        assert_eq!(port_from_location_id(0x14320000), Some(2));
+        assert_eq!(port_from_location_id(0x14100000), Some(1));
+        assert_eq!(port_from_location_id(0x14000000), None);
+    }
+
+    #[test]
+    fn test_device_id() {
+        let number = |n: i64| CFNumber::from(n).as_CFType();
+        let properties = CFDictionary::from_CFType_pairs(&[
+            (CFString::new("idVendor"), number(0x046d)),
+            (CFString::new("idProduct"), number(0xc52b)),
+            (CFString::new("locationID"), number(0x14320000)),
+            (CFString::new("sessionID"), number(4295000000)),
+        ]);
+        assert_eq!(
+            device_id(&properties),
+            Some("14320000:046d:c52b".to_string())
+        );
+
+        // Without a location the session ID is used
+        let properties = CFDictionary::from_CFType_pairs(&[
+            (CFString::new("idVendor"), number(0x046d)),
+            (CFString::new("idProduct"), number(0xc52b)),
+            (CFString::new("sessionID"), number(4295000000)),
+        ]);
+        assert_eq!(device_id(&properties), Some("4295000000".to_string()));
+
+        assert_eq!(device_id(&CFDictionary::from_CFType_pairs(&[])), None);
+    }
+
+    #[test]
+    fn test_device_from_properties() {
+        let number = |n: i64| CFNumber::from(n).as_CFType();
+        let properties = CFDictionary::from_CFType_pairs(&[
+            (CFString::new("idVendor"), number(0x0ce9)),
+            (CFString::new("idProduct"), number(0x1220)),
+            (CFString::new("locationID"), number(0x14320000)),
+            (CFString::new("bDeviceClass"), number(0x42)),
+            (CFString::new("bcdDevice"), number(0x0214)),
+            (
+                CFString::new("USB Product Name"),
+                CFString::new("PicoScope 4000 series").as_CFType(),
+            ),
+        ]);
+
+        let device = device_from_properties(&properties, "test", None, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(device.id, "14320000:0ce9:1220");
+        assert_eq!(device.vendor_id, 0x0ce9);
+        assert_eq!(device.product_id, 0x1220);
+        assert_eq!(device.description.as_deref(), Some("PicoScope 4000 series"));
+        assert_eq!(device.serial_number, None);
+        assert_eq!(device.base_class, Some(DeviceBaseClass::Unknown(0x42)));
+        assert_eq!(device.device_version, Some((2, 14)));
+        assert_eq!(device.bus_number, Some(0x14));
+        assert_eq!(device.port_number, Some(2));
+
+        assert_eq!(
+            device_from_properties(&properties, "test", Some(0x1234), None),
+            Ok(None)
+        );
+
+        let missing = CFDictionary::from_CFType_pairs(&[]);
+        assert!(device_from_properties(&missing, "test", None, None).is_err());
+    }
+
+    #[test]
+    fn test_interface_from() {
+        let number = |n: i32| CFNumber::from(n).as_CFType();
+        let properties = CFDictionary::from_CFType_pairs(&[
+            (CFString::new("bInterfaceNumber"), number(1)),
+            (CFString::new("bInterfaceClass"), number(3)),
+            (CFString::new("bInterfaceSubClass"), number(1)),
+            (CFString::new("bInterfaceProtocol"), number(2)),
+        ]);
+
+        assert_eq!(
+            interface_from(&properties),
+            Some(UsbInterface {
+                number: 1,
+                base_class: DeviceBaseClass::HumanInterfaceDevice,
+                sub_class: 1,
+                protocol: 2,
+                description: None,
+            })
+        );
+
+        // Drivers and other children have no interface number
+        assert_eq!(interface_from(&CFDictionary::from_CFType_pairs(&[])), None);
+    }
 }
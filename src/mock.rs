@@ -0,0 +1,246 @@
+use crate::{enumerate_platform_with, DeviceError, Error, UsbDevice};
+use std::sync::{Mutex, MutexGuard};
+
+// Shared by every thread so Observers' background threads see the same
+// devices as the test that set them
+static MOCK_DEVICES: Mutex<Option<Vec<UsbDevice>>> = Mutex::new(None);
+
+fn mock_devices() -> MutexGuard<'static, Option<Vec<UsbDevice>>> {
+    MOCK_DEVICES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Replaces the connected devices with mock devices
+///
+/// Until [`clear_mock_devices`] is called, enumeration returns these devices
+/// instead of asking the OS, and `Observer`s poll for changes at their poll
+/// interval rather than waiting for OS device notifications. The mock
+/// devices are shared by all threads.
+pub fn set_mock_devices(devices: Vec<UsbDevice>) {
+    *mock_devices() = Some(devices);
+}
+
+/// Removes the mock devices so enumeration asks the OS again
+pub fn clear_mock_devices() {
+    *mock_devices() = None;
+}
+
+/// Adds a mock device, which `Observer`s report as connected on their next
+/// poll
+///
+/// If no mock devices have been set this starts with no devices.
+pub fn push_mock_connect(device: UsbDevice) {
+    mock_devices().get_or_insert_with(Vec::new).push(device);
+}
+
+/// Removes the mock device with the given `id`, which `Observer`s report as
+/// disconnected on their next poll
+pub fn push_mock_disconnect(id: &str) {
+    if let Some(devices) = mock_devices().as_mut() {
+        devices.retain(|device| device.id != id);
+    }
+}
+
+// Only used by the Observer and the Linux `find_by_id` fast path
+#[cfg_attr(not(any(feature = "observer", target_os = "linux")), allow(dead_code))]
+pub(crate) fn mock_active() -> bool {
+    mock_devices().is_some()
+}
+
+/// Enumerates the mock devices if they've been set and otherwise the
+/// platform devices
+pub(crate) fn enumerate_source(
+    vid: Option<u16>,
+    pid: Option<u16>,
+    include_non_present: bool,
+) -> Result<impl Iterator<Item = Result<UsbDevice, DeviceError>>, Error> {
+    let mock = mock_devices().clone().map(|devices| {
+        devices
+            .into_iter()
+            .filter(move |device| device.matches(vid, pid))
+    });
+
+    let platform = match mock {
+        Some(_) => None,
+        None => Some(enumerate_platform_with(vid, pid, include_non_present)?),
+    };
+
+    Ok(mock
+        .into_iter()
+        .flatten()
+        .map(Ok)
+        .chain(platform.into_iter().flatten()))
+}
+
+/// Serializes the tests that set mock devices with the tests that enumerate
+/// the OS, which would otherwise see or lose the mock devices mid-test
+#[cfg(test)]
+pub(crate) fn test_lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::test_device, DeviceBaseClass};
+
+    /// Holds the test lock while mock devices are set and clears them when
+    /// dropped, even if the test panics
+    struct Mocked {
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for Mocked {
+        fn drop(&mut self) {
+            clear_mock_devices();
+        }
+    }
+
+    fn mock(devices: Vec<UsbDevice>) -> Mocked {
+        let _lock = test_lock();
+        set_mock_devices(devices);
+        Mocked { _lock }
+    }
+
+    // Use a vendor ID no other test filters on
+    fn mock_device(id: &str) -> UsbDevice {
+        UsbDevice {
+            vendor_id: 0xfffe,
+            ..test_device(id, "Mock")
+        }
+    }
+
+    fn with_serial() -> UsbDevice {
+        UsbDevice {
+            serial_number: Some("SERIAL-1".to_string()),
+            ..mock_device("serial")
+        }
+    }
+
+    fn hub() -> UsbDevice {
+        UsbDevice {
+            base_class: Some(DeviceBaseClass::Hub),
+            ..mock_device("hub")
+        }
+    }
+
+    #[test]
+    fn test_mock_devices() {
+        let _mocked = mock(vec![mock_device("1")]);
+
+        let devices = crate::enumerate(Some(0xfffe), None).unwrap();
+        assert_eq!(devices, vec![mock_device("1")]);
+        assert_eq!(crate::find_by_id("1"), Some(mock_device("1")));
+        assert_eq!(crate::find_by_id("missing"), None);
+        assert_eq!(crate::enumerate_count(Some(0xfffe), None), Ok(1));
+        assert_eq!(
+            crate::enumerate_including_non_present(Some(0xfffe), None).unwrap(),
+            vec![mock_device("1")]
+        );
+
+        push_mock_connect(mock_device("2"));
+        push_mock_disconnect("1");
+        assert_eq!(
+            crate::enumerate(Some(0xfffe), None).unwrap(),
+            vec![mock_device("2")]
+        );
+    }
+
+    #[test]
+    fn test_enumerate_by_serial() {
+        let _mocked = mock(vec![mock_device("1"), with_serial()]);
+
+        assert_eq!(
+            crate::enumerate_by_serial("SERIAL-1").unwrap(),
+            vec![with_serial()]
+        );
+        assert!(crate::enumerate_by_serial("SERIAL").unwrap().is_empty());
+    }
+
+    #[cfg(feature = "observer")]
+    #[test]
+    fn test_with_serial() {
+        use crate::Observer;
+
+        let _mocked = mock(vec![mock_device("1"), with_serial()]);
+
+        let observer = Observer::new().with_vendor_id(0xfffe);
+        assert_eq!(
+            observer.clone().with_serial("SERIAL-1").snapshot(),
+            vec![with_serial()]
+        );
+        assert!(observer.clone().with_serial("SERIAL").snapshot().is_empty());
+        assert_eq!(
+            observer.with_serial_contains("SERIAL").snapshot(),
+            vec![with_serial()]
+        );
+    }
+
+    #[test]
+    fn test_enumerate_by_class() {
+        let _mocked = mock(vec![mock_device("1"), hub()]);
+
+        assert_eq!(
+            crate::enumerate_by_class(DeviceBaseClass::Hub).unwrap(),
+            vec![hub()]
+        );
+    }
+
+    #[test]
+    fn test_enumerate_with() {
+        let _mocked = mock(vec![mock_device("1"), hub()]);
+
+        assert_eq!(
+            crate::enumerate_with(crate::EnumerateOptions {
+                vendor_id: Some(0xfffe),
+                class: Some(DeviceBaseClass::Hub),
+                include_non_present: true,
+                ..Default::default()
+            })
+            .unwrap(),
+            vec![hub()]
+        );
+    }
+
+    #[cfg(feature = "observer")]
+    #[test]
+    fn test_mock_observer() {
+        use crate::{Event, Observer};
+        use std::time::Duration;
+
+        let _mocked = mock(vec![mock_device("1"), hub()]);
+        let timeout = Duration::from_secs(5);
+
+        let subscription = Observer::new().with_class(DeviceBaseClass::Hub).subscribe();
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices == [hub()]
+        ));
+        subscription.unsubscribe().unwrap();
+        push_mock_disconnect("hub");
+
+        let subscription = Observer::new()
+            .with_vendor_id(0xfffe)
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .subscribe();
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices == [mock_device("1")]
+        ));
+
+        push_mock_connect(mock_device("2"));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Connect(device)) if device.id == "2"
+        ));
+
+        push_mock_disconnect("1");
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Disconnect(device)) if device.id == "1"
+        ));
+        subscription.unsubscribe().unwrap();
+    }
+}
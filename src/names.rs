@@ -0,0 +1,48 @@
+use crate::common::UsbDevice;
+use usb_ids::FromId;
+
+/// Fills in the manufacturer and product name from the USB ID database
+/// where the OS didn't provide them
+pub(crate) fn fill_names(mut device: UsbDevice) -> UsbDevice {
+    if device.manufacturer.is_none() {
+        device.manufacturer =
+            usb_ids::Vendor::from_id(device.vendor_id).map(|vendor| vendor.name().to_string());
+    }
+
+    if device.product_name.is_none() {
+        device.product_name = usb_ids::Device::from_vid_pid(device.vendor_id, device.product_id)
+            .map(|product| product.name().to_string());
+    }
+
+    device
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_device;
+
+    #[test]
+    fn test_fill_names() {
+        let mut device = test_device("1", "Device");
+        device.vendor_id = 0x1d6b;
+        device.product_id = 0x0002;
+
+        let device = fill_names(device);
+        assert_eq!(device.manufacturer.as_deref(), Some("Linux Foundation"));
+        assert_eq!(device.product_name.as_deref(), Some("2.0 root hub"));
+    }
+
+    #[test]
+    fn test_fill_names_keeps_os_strings() {
+        let mut device = test_device("1", "Device");
+        device.vendor_id = 0x1d6b;
+        device.product_id = 0x0002;
+        device.manufacturer = Some("From OS".to_string());
+        device.product_name = Some("From OS".to_string());
+
+        let device = fill_names(device);
+        assert_eq!(device.manufacturer.as_deref(), Some("From OS"));
+        assert_eq!(device.product_name.as_deref(), Some("From OS"));
+    }
+}
@@ -0,0 +1,1910 @@
+use crate::{
+    callbacks::Callbacks,
+    channel::{
+        bounded, unbounded, BoundedSender, Receiver, RecvError, RecvTimeoutError, TryRecvError,
+    },
+    common::*,
+    enumerate_matching, matches_any, retry, single_id,
+    stats::ObserverStats,
+    Error, HotplugMonitor,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Sent to the background thread by its subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Signal {
+    /// Stop the thread
+    Close,
+    /// Re-enumerate now rather than waiting for the next poll
+    Refresh,
+}
+
+/// How often the background thread checks whether its subscription has been
+/// dropped
+pub(crate) const CLOSE_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Poll interval used when polling is disabled but OS device notifications
+/// aren't available
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// # Waits for a device to be connected
+///
+/// * `vendor_id` - USB Vendor ID to wait for
+/// * `product_id` - USB Product ID to wait for
+/// * `timeout` - How long to wait before giving up
+///
+/// Returns as soon as a matching device is connected, including if one is
+/// already connected, or `None` if the timeout elapses first. The background
+/// thread is closed on return.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let device = usb_enumeration::wait_for_device(0x1234, 0x5678, Duration::from_secs(10))
+///     .expect("Device did not connect");
+/// ```
+pub fn wait_for_device(vendor_id: u16, product_id: u16, timeout: Duration) -> Option<UsbDevice> {
+    let subscription = Observer::new()
+        .with_vendor_id(vendor_id)
+        .with_product_id(product_id)
+        .subscribe();
+
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        match subscription.rx_event.recv_timeout(remaining).ok()? {
+            Event::Initial(devices) => {
+                if let Some(device) = devices.into_iter().next() {
+                    return Some(device);
+                }
+            }
+            Event::Connect(device) => return Some(device),
+            _ => {}
+        }
+    }
+}
+
+/// # Waits for a device to be disconnected
+///
+/// * `id` - The `id` of the device to wait for
+/// * `timeout` - How long to wait before giving up
+///
+/// Returns `true` as soon as the device is disconnected, including if it
+/// isn't connected to begin with, or `false` if the timeout elapses first.
+/// The background thread is closed on return.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// # let device = usb_enumeration::enumerate(None, None)?.remove(0);
+/// if !usb_enumeration::wait_for_disconnect(&device.id, Duration::from_secs(10)) {
+///     println!("Device is still connected");
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn wait_for_disconnect(id: &str, timeout: Duration) -> bool {
+    let subscription = Observer::new().subscribe();
+
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        match subscription.rx_event.recv_timeout(remaining) {
+            Ok(Event::Initial(devices)) => {
+                if !devices.iter().any(|device| device.id == id) {
+                    return true;
+                }
+            }
+            Ok(Event::Disconnect(device)) => {
+                if device.id == id {
+                    return true;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Events send from the Observer
+///
+/// More events may be added in future so matches should include a wildcard
+/// arm. Events compare devices by their `id`, like [`UsbDevice`] does, so
+/// `Changed` events compare equal whenever they're for the same device.
+// Boxing the devices in `Changed` would make it awkward to match on
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    /// Initial list of devices when polling starts
+    Initial(Vec<UsbDevice>),
+    /// A device that has just been connected
+    Connect(UsbDevice),
+    /// A device that has just disconnected
+    Disconnect(UsbDevice),
+    /// A device that disconnected and connected again within the window set
+    /// by [`Observer::with_reconnect_debounce`], sent instead of a
+    /// `Disconnect` followed by a `Connect`
+    Reconnect(UsbDevice),
+    /// A connected device whose details have changed, such as its description
+    Changed {
+        /// The device as it was previously reported
+        previous: UsbDevice,
+        /// The device as it is now reported
+        current: UsbDevice,
+    },
+    /// All the changes found in one poll, sent instead of the individual
+    /// events when [`Observer::with_batched_events`] is enabled
+    Batch {
+        /// Devices that have just been connected
+        connected: Vec<UsbDevice>,
+        /// Devices that have just disconnected
+        disconnected: Vec<UsbDevice>,
+        /// Devices whose details have changed, as `(previous, current)`
+        changed: Vec<(UsbDevice, UsbDevice)>,
+        /// Devices that reconnected, see [`Event::Reconnect`]
+        reconnected: Vec<UsbDevice>,
+    },
+}
+
+/// An [`Event`] with the time the background thread observed it
+///
+/// Sent by the subscription from [`Observer::subscribe_timed`]. The times are
+/// taken before the event is sent, so they aren't affected by how long the
+/// event waits in the channel.
+#[derive(Debug, Clone)]
+pub struct TimedEvent {
+    /// When the event was observed, for measuring time between events
+    pub at: Instant,
+    /// When the event was observed, as wall-clock time for logging
+    pub time: SystemTime,
+    pub event: Event,
+}
+
+/// Receives events from the background thread started by
+/// [`Observer::subscribe`] or [`Observer::subscribe_timed`]
+///
+/// With the default crossbeam channels this can be cloned to receive events
+/// on multiple threads. Each event is received by only one of the clones and
+/// the background thread keeps running until every clone has been dropped.
+/// With the `std-channels` feature `rx_event` is a
+/// `std::sync::mpsc::Receiver`, which can't be cloned.
+///
+/// The receive methods return a `Disconnected` error once the background
+/// thread has exited and every event it sent has been received, which can be
+/// used to detect that it has stopped:
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use usb_enumeration::{Observer, RecvTimeoutError};
+///
+/// let subscription = Observer::new().subscribe();
+///
+/// loop {
+///     match subscription.recv_timeout(Duration::from_millis(100)) {
+///         Ok(event) => println!("{:?}", event),
+///         Err(RecvTimeoutError::Timeout) => { /* do other work */ }
+///         Err(RecvTimeoutError::Disconnected) => break,
+///     }
+/// }
+/// ```
+#[cfg_attr(
+    not(any(feature = "std-channels", not(feature = "crossbeam"))),
+    derive(Clone)
+)]
+pub struct Subscription<E = Event> {
+    pub rx_event: Receiver<E>,
+    // When every clone of this gets dropped, the channel will become
+    // disconnected and the background thread will close
+    pub(crate) tx_close: BoundedSender<Signal>,
+    pub(crate) thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+    pub(crate) stats: Arc<ObserverStats>,
+}
+
+impl<E> Subscription<E> {
+    /// Returns a handle to the background thread's poll statistics
+    ///
+    /// The statistics are updated once per poll.
+    pub fn stats(&self) -> Arc<ObserverStats> {
+        self.stats.clone()
+    }
+
+    /// Returns `true` while the background thread is running
+    ///
+    /// A panic while enumerating, such as inside a platform call or a custom
+    /// source, only skips that poll and the thread carries on. A panic
+    /// anywhere else, such as in a callback, ends the thread so this returns
+    /// `false` and no more events are sent, although events that were
+    /// already sent can still be received.
+    pub fn is_alive(&self) -> bool {
+        self.stats.is_alive()
+    }
+
+    /// Blocks until the next event is received
+    ///
+    /// Returns an error if the background thread has exited.
+    pub fn recv(&self) -> Result<E, RecvError> {
+        self.rx_event.recv()
+    }
+
+    /// Returns the next event if one has already been received
+    pub fn try_recv(&self) -> Result<E, TryRecvError> {
+        self.rx_event.try_recv()
+    }
+
+    /// Waits up to `timeout` for the next event
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<E, RecvTimeoutError> {
+        self.rx_event.recv_timeout(timeout)
+    }
+
+    /// Blocks until the next event is received
+    ///
+    /// Returns `None` once the background thread has exited and every event
+    /// it sent has been received.
+    ///
+    /// ```no_run
+    /// let subscription = usb_enumeration::Observer::new().subscribe();
+    ///
+    /// while let Some(event) = subscription.next_event() {
+    ///     println!("{:?}", event);
+    /// }
+    /// ```
+    pub fn next_event(&self) -> Option<E> {
+        self.rx_event.recv().ok()
+    }
+
+    /// Blocks for up to `timeout` until the next event is received
+    ///
+    /// Returns `None` if no event arrived in time or the background thread
+    /// has exited. Use [`Subscription::recv_timeout`] to tell these apart.
+    pub fn next_event_timeout(&self, timeout: Duration) -> Option<E> {
+        self.rx_event.recv_timeout(timeout).ok()
+    }
+
+    /// Iterates over the events that have already been received without
+    /// blocking
+    ///
+    /// The iterator ends when there are no more pending events, whether or
+    /// not the background thread is still running.
+    pub fn try_iter(&self) -> impl Iterator<Item = E> + '_ {
+        self.rx_event.try_iter()
+    }
+
+    /// Re-enumerates devices now rather than waiting for the next poll or OS
+    /// device notification
+    ///
+    /// This is useful after the system resumes from sleep, when the devices
+    /// may have changed without a notification. Changes since the last poll
+    /// are sent as usual, so if the devices changed during sleep this can
+    /// send a burst of events. Refreshes requested before the background
+    /// thread gets to the first are combined with it.
+    pub fn refresh(&self) {
+        // A full channel already has a refresh or close pending
+        let _ = self.tx_close.try_send(Signal::Refresh);
+    }
+
+    /// Stops the background thread and blocks until it has exited
+    ///
+    /// Dropping a `Subscription` also stops the background thread once every
+    /// clone has been dropped, but doesn't wait for it to exit. This stops
+    /// the thread even if there are other clones of the subscription, and
+    /// only the first clone to call this waits for the thread.
+    ///
+    /// Returns an error if the background thread panicked.
+    pub fn unsubscribe(self) -> thread::Result<()> {
+        // This only waits while a refresh is pending and only fails if the
+        // thread has exited
+        let _ = self.tx_close.send(Signal::Close);
+
+        let handle = self
+            .thread
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+
+        match handle {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Filter(Arc<dyn Fn(&UsbDevice) -> bool + Send + Sync>);
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Filter")
+    }
+}
+
+type SourceFn = dyn Fn(Option<u16>, Option<u16>) -> Result<Vec<UsbDevice>, Error> + Send + Sync;
+
+#[derive(Clone)]
+struct Source(Arc<SourceFn>);
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Source")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Observer {
+    poll_interval: Duration,
+    poll_jitter: Duration,
+    vendor_ids: Vec<u16>,
+    product_ids: Vec<u16>,
+    excluded_vendor_ids: Vec<u16>,
+    excluded_product_ids: Vec<u16>,
+    classes: Vec<DeviceBaseClass>,
+    serial: Option<String>,
+    serial_contains: Option<String>,
+    filter: Option<Filter>,
+    source: Option<Source>,
+    pub(crate) callbacks: Callbacks,
+    hotplug: bool,
+    stable_ids: bool,
+    batched_events: bool,
+    initial: bool,
+    initial_as_connects: bool,
+    reconnect_debounce: Duration,
+    include_non_present: bool,
+    merged: Vec<Observer>,
+    enumerate_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl Default for Observer {
+    fn default() -> Self {
+        Observer::new()
+    }
+}
+
+impl Observer {
+    /// Create a new Observer with the poll interval specified in seconds
+    pub fn new() -> Self {
+        Observer {
+            poll_interval: Duration::from_secs(1),
+            poll_jitter: Duration::ZERO,
+            vendor_ids: Vec::new(),
+            product_ids: Vec::new(),
+            excluded_vendor_ids: Vec::new(),
+            excluded_product_ids: Vec::new(),
+            classes: Vec::new(),
+            serial: None,
+            serial_contains: None,
+            filter: None,
+            source: None,
+            callbacks: Callbacks::default(),
+            hotplug: cfg!(any(target_os = "linux", target_os = "macos")),
+            stable_ids: false,
+            batched_events: false,
+            initial: true,
+            initial_as_connects: false,
+            reconnect_debounce: Duration::ZERO,
+            include_non_present: false,
+            merged: Vec::new(),
+            enumerate_retries: 0,
+            retry_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Set the poll interval in seconds
+    ///
+    /// The interval is only used when OS device notifications are disabled
+    /// or unavailable, and to retry a failed enumeration. Notifications are
+    /// used by default on Linux and macOS, see [`Observer::with_hotplug`]. An
+    /// interval of zero disables polling, see [`Observer::without_polling`].
+    pub fn with_poll_interval(self, seconds: u32) -> Self {
+        self.with_poll_interval_duration(Duration::from_secs(seconds.into()))
+    }
+
+    /// Set the poll interval, allowing for sub-second polling
+    ///
+    /// The interval is only used when OS device notifications are disabled
+    /// or unavailable, and to retry a failed enumeration. Notifications are
+    /// used by default on Linux and macOS, see [`Observer::with_hotplug`]. An
+    /// interval of zero disables polling, see [`Observer::without_polling`].
+    pub fn with_poll_interval_duration(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Randomize each poll by up to `jitter` either side of the poll interval
+    ///
+    /// This stops many Observers started together from polling in lockstep.
+    /// The jitter is capped at the poll interval and defaults to zero.
+    pub fn with_poll_jitter(mut self, jitter: Duration) -> Self {
+        self.poll_jitter = jitter;
+        self
+    }
+
+    /// Only re-enumerate devices when the OS reports a device change
+    ///
+    /// This enables [`Observer::with_hotplug`] and disables polling. If OS
+    /// device notifications can't be set up, devices are polled every second
+    /// rather than not at all.
+    pub fn without_polling(self) -> Self {
+        self.with_hotplug(true)
+            .with_poll_interval_duration(Duration::ZERO)
+    }
+
+    /// Filter results by USB Vendor ID
+    ///
+    /// This can be combined with other vendor ID filters, in which case
+    /// devices matching any of the vendor IDs are included.
+    pub fn with_vendor_id(self, vendor_id: u16) -> Self {
+        self.with_vendor_ids(Some(vendor_id))
+    }
+
+    /// Filter results by USB Product ID
+    ///
+    /// This can be combined with other product ID filters, in which case
+    /// devices matching any of the product IDs are included.
+    pub fn with_product_id(self, product_id: u16) -> Self {
+        self.with_product_ids(Some(product_id))
+    }
+
+    /// Filter results to any of the given USB Vendor IDs
+    pub fn with_vendor_ids(mut self, vendor_ids: impl IntoIterator<Item = u16>) -> Self {
+        for vendor_id in vendor_ids {
+            if !self.vendor_ids.contains(&vendor_id) {
+                self.vendor_ids.push(vendor_id);
+            }
+        }
+        self
+    }
+
+    /// Filter results to any of the given USB Product IDs
+    pub fn with_product_ids(mut self, product_ids: impl IntoIterator<Item = u16>) -> Self {
+        for product_id in product_ids {
+            if !self.product_ids.contains(&product_id) {
+                self.product_ids.push(product_id);
+            }
+        }
+        self
+    }
+
+    /// Use OS device notifications to detect changes instead of polling
+    ///
+    /// This is enabled by default on Linux, where it uses udev events, and on
+    /// macOS, where it uses IOKit matching notifications. On Windows it is
+    /// disabled by default and enabling it creates a hidden window to receive
+    /// `WM_DEVICECHANGE` messages. If notifications can't be set up, devices
+    /// are polled at the configured interval instead.
+    pub fn with_hotplug(mut self, enabled: bool) -> Self {
+        self.hotplug = enabled;
+        self
+    }
+
+    /// Match devices between polls by [`UsbDevice::stable_id`] rather than
+    /// the platform `id`
+    ///
+    /// With this enabled, a device with a serial number that moves to another
+    /// port or gets a new platform `id` is reported as `Changed` rather than
+    /// as a `Disconnect` followed by a `Connect`.
+    pub fn with_stable_ids(mut self, enabled: bool) -> Self {
+        self.stable_ids = enabled;
+        self
+    }
+
+    /// Include devices Windows remembers but that aren't connected
+    ///
+    /// See [`enumerate_including_non_present`](crate::enumerate_including_non_present). These devices are reported
+    /// in the `Initial` event and, since Windows keeps them when they
+    /// disconnect, connecting or disconnecting them is reported as `Changed`
+    /// with a different [`present`](UsbDevice::present). This has no effect
+    /// on other platforms.
+    pub fn with_non_present(mut self, enabled: bool) -> Self {
+        self.include_non_present = enabled;
+        self
+    }
+
+    /// Send an [`Event::Reconnect`] rather than a `Disconnect` and `Connect`
+    /// when a device reconnects within `window`
+    ///
+    /// Devices are matched by `id`, or by [`UsbDevice::stable_id`] when
+    /// [`Observer::with_stable_ids`] is enabled. On macOS, devices without a
+    /// `locationID` get a new `id` on every connection, so stable ids are
+    /// needed to match them. Every
+    /// `Disconnect` is delayed by `window`, plus up to one poll interval
+    /// when polling. A zero window, the default, disables this.
+    pub fn with_reconnect_debounce(mut self, window: Duration) -> Self {
+        self.reconnect_debounce = window;
+        self
+    }
+
+    /// Send the changes found in each poll as a single [`Event::Batch`]
+    /// rather than as individual events
+    ///
+    /// Batching is per poll, so events are delayed by at most the poll
+    /// interval or until the next OS device notification. Polls without
+    /// changes send nothing and the `Initial` event is unchanged.
+    pub fn with_batched_events(mut self, enabled: bool) -> Self {
+        self.batched_events = enabled;
+        self
+    }
+
+    /// Whether to report the devices that are connected when the Observer
+    /// starts, which is enabled by default
+    ///
+    /// When disabled the first enumeration is only used as the baseline for
+    /// later changes, so the first event is a `Connect` or `Disconnect` for a
+    /// change after startup rather than the connected devices.
+    /// [`Observer::with_initial_as_connects`] then has no effect. If the first
+    /// enumeration fails it is retried at the poll interval, so nothing is
+    /// sent until it succeeds.
+    pub fn with_initial(mut self, enabled: bool) -> Self {
+        self.initial = enabled;
+        self
+    }
+
+    /// Send a `Connect` event for each device that's connected when the
+    /// Observer starts instead of a single `Initial` event
+    ///
+    /// These devices are then known to the Observer, so they aren't reported
+    /// as connected again by later polls.
+    pub fn with_initial_as_connects(mut self, enabled: bool) -> Self {
+        self.initial_as_connects = enabled;
+        self
+    }
+
+    /// Retry a failed enumeration up to `count` times, waiting `backoff`
+    /// before the first retry and doubling the wait for each retry after that
+    ///
+    /// This helps when the Observer starts while the OS is still starting up,
+    /// see [`enumerate_with_retry`](crate::enumerate_with_retry). A poll is only skipped once every retry
+    /// has failed. The retries delay unsubscribing, so keep the total backoff
+    /// short. Defaults to no retries.
+    pub fn with_enumerate_retries(mut self, count: u32, backoff: Duration) -> Self {
+        self.enumerate_retries = count;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Exclude devices with this USB Vendor ID
+    ///
+    /// Exclusions are applied after the vendor and product ID filters and
+    /// take precedence over them, so a device matching both is excluded.
+    pub fn without_vendor_id(mut self, vendor_id: u16) -> Self {
+        if !self.excluded_vendor_ids.contains(&vendor_id) {
+            self.excluded_vendor_ids.push(vendor_id);
+        }
+        self
+    }
+
+    /// Exclude devices with this USB Product ID
+    ///
+    /// Exclusions are applied after the vendor and product ID filters and
+    /// take precedence over them, so a device matching both is excluded.
+    pub fn without_product_id(mut self, product_id: u16) -> Self {
+        if !self.excluded_product_ids.contains(&product_id) {
+            self.excluded_product_ids.push(product_id);
+        }
+        self
+    }
+
+    /// Only match the device with exactly this serial number
+    ///
+    /// Devices without a serial number never match.
+    pub fn with_serial(mut self, serial: impl Into<String>) -> Self {
+        self.serial = Some(serial.into());
+        self
+    }
+
+    /// Only match devices whose serial number contains `serial`
+    ///
+    /// Devices without a serial number never match.
+    pub fn with_serial_contains(mut self, serial: impl Into<String>) -> Self {
+        self.serial_contains = Some(serial.into());
+        self
+    }
+
+    /// Also watch the devices matched by the filters of `others`
+    ///
+    /// Devices are reported when they match this Observer's filters or those
+    /// of any of `others`, and a device matching several is only reported
+    /// once. Everything else, such as the poll interval, is taken from this
+    /// Observer, so it still uses a single background thread that enumerates
+    /// once per poll.
+    ///
+    /// ```no_run
+    /// use usb_enumeration::{DeviceBaseClass, Observer};
+    ///
+    /// let subscription = Observer::new()
+    ///     .with_vendor_id(0x1234)
+    ///     .merge(vec![Observer::new().with_class(DeviceBaseClass::Hub)])
+    ///     .subscribe();
+    /// ```
+    pub fn merge(mut self, others: Vec<Observer>) -> Self {
+        self.merged.extend(others);
+        self
+    }
+
+    /// Filter results by base class
+    ///
+    /// Can be called more than once to match any of several classes. Devices
+    /// match when the device or any of its interfaces reports the class, see
+    /// [`UsbDevice::has_class`].
+    pub fn with_class(mut self, class: DeviceBaseClass) -> Self {
+        if !self.classes.contains(&class) {
+            self.classes.push(class);
+        }
+        self
+    }
+
+    /// Filter results by USB Vendor and Product ID
+    pub fn with_vid_pid(self, vid_pid: VidPid) -> Self {
+        self.with_vendor_id(vid_pid.vendor_id)
+            .with_product_id(vid_pid.product_id)
+    }
+
+    /// Filter results with a custom predicate
+    ///
+    /// The predicate runs after the vendor and product ID filters so it only
+    /// sees devices that have already matched those. It is shared with the
+    /// background thread and therefore needs to be `Send` and `Sync`.
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(&UsbDevice) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Filter(Arc::new(filter)));
+        self
+    }
+
+    /// Enumerate devices with the given function rather than from the OS
+    ///
+    /// The function is called with the same vendor and product ID arguments
+    /// as [`enumerate`](crate::enumerate) and its results are filtered the same way. This is
+    /// intended for testing code that uses an `Observer` with scripted
+    /// device lists. Returning an error skips that poll. OS device
+    /// notifications aren't used with a custom source so it is called at the
+    /// poll interval.
+    pub fn with_source(
+        mut self,
+        source: impl Fn(Option<u16>, Option<u16>) -> Result<Vec<UsbDevice>, Error>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.source = Some(Source(Arc::new(source)));
+        self
+    }
+
+    fn enumerate(&self) -> Result<Vec<UsbDevice>, Error> {
+        // Merged Observers can match devices with any IDs
+        let (vendor_ids, product_ids): (&[u16], &[u16]) = if self.merged.is_empty() {
+            (&self.vendor_ids, &self.product_ids)
+        } else {
+            (&[], &[])
+        };
+
+        let mut devices = match &self.source {
+            Some(Source(source)) => source(single_id(vendor_ids), single_id(product_ids))?,
+            None => enumerate_matching(vendor_ids, product_ids, self.include_non_present)?,
+        };
+        devices.retain(|device| self.accepts(device));
+
+        debug!(
+            "Enumerated {} devices (vendor_ids: {:04x?}, product_ids: {:04x?}, excluded_vendor_ids: {:04x?}, excluded_product_ids: {:04x?})",
+            devices.len(),
+            self.vendor_ids,
+            self.product_ids,
+            self.excluded_vendor_ids,
+            self.excluded_product_ids
+        );
+
+        Ok(devices)
+    }
+
+    /// Returns `true` if the device matches all of this Observer's filters,
+    /// or those of any Observer merged into it
+    fn accepts(&self, device: &UsbDevice) -> bool {
+        (matches_any(device, &self.vendor_ids, &self.product_ids) && self.matches(device))
+            || self.merged.iter().any(|observer| observer.accepts(device))
+    }
+
+    /// Applies the filters that run after the vendor and product IDs have
+    /// been matched
+    fn matches(&self, device: &UsbDevice) -> bool {
+        if self.excluded_vendor_ids.contains(&device.vendor_id)
+            || self.excluded_product_ids.contains(&device.product_id)
+        {
+            return false;
+        }
+
+        if !self.classes.is_empty() && !self.classes.iter().any(|&class| device.has_class(class)) {
+            return false;
+        }
+
+        let serial = device.serial_number.as_deref();
+        if self.serial.as_deref().is_some_and(|s| serial != Some(s))
+            || self
+                .serial_contains
+                .as_deref()
+                .is_some_and(|s| !serial.is_some_and(|serial| serial.contains(s)))
+        {
+            return false;
+        }
+
+        match &self.filter {
+            Some(Filter(filter)) => filter(device),
+            None => true,
+        }
+    }
+
+    /// Enumerate the currently connected devices that match this Observer's
+    /// filters, without subscribing
+    ///
+    /// This applies the same filtering as [`Observer::subscribe`] and
+    /// returns an empty list if the platform enumeration API fails.
+    ///
+    /// ```no_run
+    /// use usb_enumeration::Observer;
+    ///
+    /// let observer = Observer::new().with_vendor_id(0x1234);
+    /// let devices = observer.snapshot();
+    /// ```
+    pub fn snapshot(&self) -> Vec<UsbDevice> {
+        self.enumerate().unwrap_or_default()
+    }
+
+    /// Enumerates devices, recording how long it took in `stats`
+    fn enumerate_recorded(&self, stats: &ObserverStats) -> Result<Vec<UsbDevice>, Error> {
+        let started = Instant::now();
+        // A panic is treated like a failed enumeration so the thread keeps
+        // running
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            retry(self.enumerate_retries, self.retry_backoff, || {
+                self.enumerate()
+            })
+        }))
+        .unwrap_or_else(|payload| {
+            let error = Error::from_panic(payload);
+            warn!("{}", error);
+            Err(error)
+        });
+        stats.record(started.elapsed(), result.as_ref().map(Vec::len));
+        result
+    }
+
+    /// The interval to poll at when OS device notifications aren't in use
+    ///
+    /// A zero interval means polling was disabled, so rather than spinning
+    /// this falls back to a default interval.
+    fn effective_poll_interval(&self) -> Duration {
+        if self.poll_interval.is_zero() {
+            FALLBACK_POLL_INTERVAL
+        } else {
+            self.poll_interval
+        }
+    }
+
+    /// Waits for the poll interval to elapse, returning `false` if the
+    /// subscription has been dropped or unsubscribed in the meantime
+    ///
+    /// The wait ends early at `wake_at` if that's sooner, or when a refresh
+    /// is requested.
+    fn wait_for_poll(
+        &self,
+        rx_close: &Receiver<Signal>,
+        rng: &mut u64,
+        wake_at: Option<Instant>,
+    ) -> bool {
+        let mut poll_interval = jittered(self.effective_poll_interval(), self.poll_jitter, rng);
+
+        if let Some(wake_at) = wake_at {
+            poll_interval = poll_interval.min(wake_at.saturating_duration_since(Instant::now()));
+        }
+
+        // Check for close at least every 250ms, or more often if the poll
+        // interval is shorter than that
+        let close_check = poll_interval.min(CLOSE_CHECK_INTERVAL);
+        let mut wait = poll_interval;
+
+        while wait > Duration::ZERO {
+            // Check whether the subscription has been disposed or
+            // unsubscribed
+            match rx_close.recv_timeout(close_check) {
+                Ok(Signal::Close) | Err(RecvTimeoutError::Disconnected) => return false,
+                Ok(Signal::Refresh) => return true,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            wait = wait.saturating_sub(close_check);
+        }
+
+        true
+    }
+
+    /// Start the background thread and watch for device changes
+    ///
+    /// See [`Observer::with_hotplug`] for when OS device notifications are
+    /// used instead of polling.
+    pub fn subscribe(&self) -> Subscription {
+        let (tx_event, rx_event) = unbounded();
+        let (tx_close, rx_close) = bounded::<Signal>(1);
+
+        let stats = Arc::new(ObserverStats::default());
+        let thread = self.spawn(rx_close, stats.clone(), move |event| {
+            tx_event.send(event).is_ok()
+        });
+
+        Subscription {
+            rx_event,
+            tx_close,
+            thread: Arc::new(Mutex::new(Some(thread))),
+            stats,
+        }
+    }
+
+    /// Start the background thread and watch for device changes, sending
+    /// each event with the time it was observed
+    ///
+    /// ```no_run
+    /// let subscription = usb_enumeration::Observer::new().subscribe_timed();
+    ///
+    /// while let Ok(timed) = subscription.recv() {
+    ///     println!("{:?}: {:?}", timed.time, timed.event);
+    /// }
+    /// ```
+    pub fn subscribe_timed(&self) -> Subscription<TimedEvent> {
+        let (tx_event, rx_event) = unbounded();
+        let (tx_close, rx_close) = bounded::<Signal>(1);
+
+        let stats = Arc::new(ObserverStats::default());
+        let thread = self.spawn(rx_close, stats.clone(), move |event| {
+            let timed = TimedEvent {
+                at: Instant::now(),
+                time: SystemTime::now(),
+                event,
+            };
+            tx_event.send(timed).is_ok()
+        });
+
+        Subscription {
+            rx_event,
+            tx_close,
+            thread: Arc::new(Mutex::new(Some(thread))),
+            stats,
+        }
+    }
+
+    /// Spawns the background thread that watches for device changes
+    ///
+    /// `emit` is called for every event and the thread exits when it returns
+    /// `false` or `rx_close` receives a close request or is disconnected.
+    /// Every enumeration is recorded in `stats`.
+    pub(crate) fn spawn(
+        &self,
+        rx_close: Receiver<Signal>,
+        stats: Arc<ObserverStats>,
+        mut emit: impl FnMut(Event) -> bool + Send + 'static,
+    ) -> JoinHandle<()> {
+        // Set before the thread starts so a new subscription is never
+        // reported as dead
+        stats.set_alive(true);
+
+        thread::Builder::new()
+            .name("USB Enumeration Thread".to_string())
+            .spawn({
+                let this = self.clone();
+                move || {
+                    let _alive = AliveGuard(stats.clone());
+
+                    // Prefer OS device notifications, falling back to polling
+                    // when they aren't available. The monitor is created
+                    // first so no changes are missed during the initial scan.
+                    // Changes to a custom source are only seen by polling
+                    let mut monitor = if this.hotplug && this.source.is_none() {
+                        hotplug_monitor()
+                    } else {
+                        None
+                    };
+
+                    let mut rng = jitter_seed();
+
+                    // Retry the initial scan at the poll interval rather than
+                    // using an empty baseline, which would report every
+                    // device as connected once enumeration recovers
+                    let mut device_list = loop {
+                        match this.enumerate_recorded(&stats) {
+                            Ok(devices) => break devices,
+                            Err(e) => {
+                                warn!("Retrying initial scan after enumeration failed: {}", e);
+                                if !this.wait_for_poll(&rx_close, &mut rng, None) {
+                                    return;
+                                }
+                            }
+                        }
+                    };
+
+                    // Send initially connected devices, which are otherwise
+                    // only the baseline for later changes
+                    if this.initial {
+                        if this.initial_as_connects {
+                            for device in &device_list {
+                                if !emit(Event::Connect(device.clone())) {
+                                    return;
+                                }
+                            }
+                        } else if !emit(Event::Initial(device_list.clone())) {
+                            return;
+                        }
+                    }
+
+                    let mut pending = PendingDisconnects::new(this.reconnect_debounce);
+                    let mut retry_at = None;
+
+                    loop {
+                        let wake_at = pending.next_expiry().into_iter().chain(retry_at).min();
+                        let open = match &mut monitor {
+                            Some(monitor) => wait_for_hotplug(&rx_close, monitor, wake_at),
+                            None => this.wait_for_poll(&rx_close, &mut rng, wake_at),
+                        };
+
+                        if !open {
+                            return;
+                        }
+
+                        // Skip this poll if enumeration failed rather than
+                        // reporting every device as disconnected. Retry after
+                        // a poll interval, since with OS device notifications
+                        // there may be no further event to wake for
+                        retry_at = None;
+                        let next_devices = match this.enumerate_recorded(&stats) {
+                            Ok(devices) => devices,
+                            Err(e) => {
+                                warn!("Skipping poll after enumeration failed: {}", e);
+                                retry_at = Some(Instant::now() + this.effective_poll_interval());
+                                continue;
+                            }
+                        };
+
+                        let (events, key): (_, fn(&UsbDevice) -> String) = if this.stable_ids {
+                            (
+                                diff_stable(&device_list, &next_devices),
+                                UsbDevice::stable_id,
+                            )
+                        } else {
+                            (diff(&device_list, &next_devices), |device| {
+                                device.id.clone()
+                            })
+                        };
+
+                        let events = pending.debounce(events, key, Instant::now());
+
+                        let events = if this.batched_events && !events.is_empty() {
+                            vec![batch(events)]
+                        } else {
+                            events
+                        };
+
+                        for event in events {
+                            if !emit(event) {
+                                return;
+                            }
+                        }
+
+                        device_list = next_devices;
+                    }
+                }
+            })
+            .expect("Could not spawn background thread")
+    }
+}
+
+/// Clears the alive flag when the background thread exits, including by
+/// panicking
+struct AliveGuard(Arc<ObserverStats>);
+
+impl Drop for AliveGuard {
+    fn drop(&mut self) {
+        self.0.set_alive(false);
+    }
+}
+
+/// Disconnects held back by [`Observer::with_reconnect_debounce`] until
+/// their device reconnects or the window expires
+struct PendingDisconnects {
+    window: Duration,
+    devices: Vec<(Instant, UsbDevice)>,
+}
+
+impl PendingDisconnects {
+    fn new(window: Duration) -> Self {
+        PendingDisconnects {
+            window,
+            devices: Vec::new(),
+        }
+    }
+
+    /// When the oldest held disconnect expires
+    fn next_expiry(&self) -> Option<Instant> {
+        self.devices.iter().map(|(expiry, _)| *expiry).min()
+    }
+
+    /// Releases the expired disconnects, then holds back new disconnects and
+    /// turns connects of held devices into reconnects
+    fn debounce(
+        &mut self,
+        events: Vec<Event>,
+        key: impl Fn(&UsbDevice) -> String,
+        now: Instant,
+    ) -> Vec<Event> {
+        if self.window.is_zero() {
+            return events;
+        }
+
+        let (expired, held) = std::mem::take(&mut self.devices)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(expiry, _)| *expiry <= now);
+        self.devices = held;
+
+        let mut debounced: Vec<Event> = expired
+            .into_iter()
+            .map(|(_, device)| Event::Disconnect(device))
+            .collect();
+
+        for event in events {
+            match event {
+                Event::Disconnect(device) => self.devices.push((now + self.window, device)),
+                Event::Connect(device) => {
+                    let held = self
+                        .devices
+                        .iter()
+                        .position(|(_, held)| key(held) == key(&device));
+
+                    match held {
+                        Some(i) => {
+                            self.devices.remove(i);
+                            debounced.push(Event::Reconnect(device));
+                        }
+                        None => debounced.push(Event::Connect(device)),
+                    }
+                }
+                event => debounced.push(event),
+            }
+        }
+
+        debounced
+    }
+}
+
+/// Seeds the poll jitter differently for every thread and process, without
+/// needing an RNG dependency
+fn jitter_seed() -> u64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hash, Hasher},
+    };
+
+    let mut hasher = RandomState::new().build_hasher();
+    thread::current().id().hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+    // xorshift gets stuck at zero
+    hasher.finish() | 1
+}
+
+/// Offsets `interval` by a random amount of up to `jitter` either side,
+/// advancing the xorshift state in `rng`
+fn jittered(interval: Duration, jitter: Duration, rng: &mut u64) -> Duration {
+    let jitter = jitter.min(interval).as_nanos() as u64;
+    if jitter == 0 {
+        return interval;
+    }
+
+    *rng ^= *rng << 13;
+    *rng ^= *rng >> 7;
+    *rng ^= *rng << 17;
+
+    let offset = *rng % (jitter.saturating_mul(2).saturating_add(1));
+    interval - Duration::from_nanos(jitter) + Duration::from_nanos(offset)
+}
+
+/// Combines the events from one poll into a single `Event::Batch`
+fn batch(events: Vec<Event>) -> Event {
+    let mut connected = Vec::new();
+    let mut disconnected = Vec::new();
+    let mut changed = Vec::new();
+    let mut reconnected = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Connect(device) => connected.push(device),
+            Event::Disconnect(device) => disconnected.push(device),
+            Event::Reconnect(device) => reconnected.push(device),
+            Event::Changed { previous, current } => changed.push((previous, current)),
+            Event::Initial(_) | Event::Batch { .. } => {}
+        }
+    }
+
+    Event::Batch {
+        connected,
+        disconnected,
+        changed,
+        reconnected,
+    }
+}
+
+/// # Computes the events between two device snapshots
+///
+/// Devices are matched by identity, so the result contains `Disconnect` for
+/// devices only in `previous`, `Connect` for devices only in `current` and
+/// `Changed` for devices in both whose other fields differ. Events are in the
+/// order the devices appear in the snapshots.
+///
+/// This is the same change detection the `Observer` uses.
+///
+/// ```no_run
+/// let previous = usb_enumeration::enumerate(None, None)?;
+/// // ...
+/// let current = usb_enumeration::enumerate(None, None)?;
+///
+/// for event in usb_enumeration::diff(&previous, &current) {
+///     println!("{:?}", event);
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn diff(previous: &[UsbDevice], current: &[UsbDevice]) -> Vec<Event> {
+    diff_by(previous, current, |device| device.id.clone())
+}
+
+/// Computes the events between two device snapshots, matching devices by
+/// [`UsbDevice::stable_id`] rather than the platform `id`
+///
+/// Devices that share a `stable_id`, such as devices with the same
+/// non-unique serial number, are matched by their platform `id` instead.
+/// See [`diff`] for details of the events returned.
+pub fn diff_stable(previous: &[UsbDevice], current: &[UsbDevice]) -> Vec<Event> {
+    diff_by(previous, current, UsbDevice::stable_id)
+}
+
+fn diff_by(
+    previous: &[UsbDevice],
+    current: &[UsbDevice],
+    key: impl Fn(&UsbDevice) -> String,
+) -> Vec<Event> {
+    // A key shared by several devices in either snapshot can't tell them
+    // apart, so those devices are matched by their platform `id` instead
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for device in previous {
+        counts.entry(key(device)).or_default().0 += 1;
+    }
+    for device in current {
+        counts.entry(key(device)).or_default().1 += 1;
+    }
+    let key = |device: &UsbDevice| {
+        let key = key(device);
+        match counts.get(&key) {
+            Some(&(previous, current)) if previous > 1 || current > 1 => device.id.clone(),
+            _ => key,
+        }
+    };
+
+    let previous_map: HashMap<String, &UsbDevice> = previous
+        .iter()
+        .map(|device| (key(device), device))
+        .collect();
+    let current_map: HashMap<String, &UsbDevice> =
+        current.iter().map(|device| (key(device), device)).collect();
+
+    // Disconnect for missing devices
+    let disconnected = previous
+        .iter()
+        .filter(|device| !current_map.contains_key(&key(device)))
+        .map(|device| Event::Disconnect(device.clone()));
+
+    // Connect for new devices
+    let connected = current
+        .iter()
+        .filter(|device| !previous_map.contains_key(&key(device)))
+        .map(|device| Event::Connect(device.clone()));
+
+    // Changed for devices whose details differ
+    let changed = current.iter().filter_map(|device| {
+        previous_map
+            .get(&key(device))
+            .filter(|previous| !previous.eq_all_fields(device))
+            .map(|previous| Event::Changed {
+                previous: (*previous).clone(),
+                current: device.clone(),
+            })
+    });
+
+    disconnected.chain(connected).chain(changed).collect()
+}
+
+/// Creates a monitor for OS device notifications, unless mock devices are in
+/// use since their changes are only seen by polling
+fn hotplug_monitor() -> Option<HotplugMonitor> {
+    #[cfg(feature = "mock")]
+    {
+        if crate::mock::mock_active() {
+            return None;
+        }
+    }
+
+    HotplugMonitor::new()
+}
+
+/// Waits for the hotplug monitor to report a device change, returning `false`
+/// if the subscription has been dropped or unsubscribed in the meantime
+///
+/// The wait ends early at `wake_at`, or when a refresh is requested.
+fn wait_for_hotplug(
+    rx_close: &Receiver<Signal>,
+    monitor: &mut HotplugMonitor,
+    wake_at: Option<Instant>,
+) -> bool {
+    loop {
+        match rx_close.try_recv() {
+            Ok(Signal::Close) | Err(TryRecvError::Disconnected) => return false,
+            Ok(Signal::Refresh) => return true,
+            Err(TryRecvError::Empty) => {}
+        }
+
+        let timeout = match wake_at {
+            Some(wake_at) => {
+                let remaining = wake_at.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return true;
+                }
+                remaining.min(CLOSE_CHECK_INTERVAL)
+            }
+            None => CLOSE_CHECK_INTERVAL,
+        };
+
+        if monitor.wait(timeout) {
+            return true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{scripted_source, test_device};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_diff_changed() {
+        let previous = vec![test_device("1", "Device")];
+        let current = vec![test_device("1", "Renamed Device")];
+
+        // A change in description is not a disconnect and reconnect
+        let events = diff(&previous, &current);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            Event::Changed { previous, current }
+                if previous.description.as_deref() == Some("Device")
+                    && current.description.as_deref() == Some("Renamed Device")
+        ));
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let previous = vec![test_device("1", "Device")];
+        let current = vec![test_device("1", "Device")];
+
+        assert!(diff(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let previous = vec![
+            test_device("1", "Device"),
+            test_device("2", "Device"),
+            test_device("3", "Device"),
+        ];
+        let current = vec![
+            test_device("2", "Device"),
+            test_device("4", "Device"),
+            test_device("5", "Device"),
+        ];
+
+        let events = diff(&previous, &current);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], Event::Disconnect(d) if d.id == "1"));
+        assert!(matches!(&events[1], Event::Disconnect(d) if d.id == "3"));
+        assert!(matches!(&events[2], Event::Connect(d) if d.id == "4"));
+        assert!(matches!(&events[3], Event::Connect(d) if d.id == "5"));
+    }
+
+    #[test]
+    fn test_diff_stable() {
+        let mut previous = test_device("1", "Device");
+        previous.serial_number = Some("ABC123".to_string());
+        let mut current = previous.clone();
+        current.id = "2".to_string();
+
+        let events = diff_stable(&[previous.clone()], &[current.clone()]);
+        assert!(matches!(&events[..], [Event::Changed { .. }]));
+
+        let events = diff(&[previous], &[current]);
+        assert!(matches!(
+            &events[..],
+            [Event::Disconnect(_), Event::Connect(_)]
+        ));
+    }
+
+    #[test]
+    fn test_diff_stable_shared_serial() {
+        let device = |id: &str| UsbDevice {
+            serial_number: Some("ABC123".to_string()),
+            ..test_device(id, "Device")
+        };
+        let (a, b) = (device("a"), device("b"));
+
+        let both = [a.clone(), b.clone()];
+        assert!(diff_stable(&both, &both).is_empty());
+
+        let events = diff_stable(&both, &both[1..]);
+        assert!(matches!(&events[..], [Event::Disconnect(device)] if *device == a));
+
+        let events = diff_stable(&both[..1], &both);
+        assert!(matches!(&events[..], [Event::Connect(device)] if *device == b));
+    }
+
+    #[test]
+    fn test_diff_empty() {
+        let devices = vec![test_device("1", "Device")];
+
+        assert!(diff(&[], &[]).is_empty());
+        assert!(matches!(&diff(&[], &devices)[..], [Event::Connect(_)]));
+        assert!(matches!(&diff(&devices, &[])[..], [Event::Disconnect(_)]));
+    }
+
+    #[test]
+    fn test_observer_ids_compose() {
+        let observer = Observer::new()
+            .with_vendor_id(0x1234)
+            .with_vendor_ids(vec![0x1234, 0x4321]);
+
+        assert_eq!(observer.vendor_ids, vec![0x1234, 0x4321]);
+    }
+
+    #[test]
+    fn test_observer_exclude_wins() {
+        let device = test_device("1", "Device");
+
+        let observer = Observer::new().with_vendor_id(0x1234);
+        assert!(matches_any(
+            &device,
+            &observer.vendor_ids,
+            &observer.product_ids
+        ));
+        assert!(observer.matches(&device));
+
+        let observer = observer.without_vendor_id(0x1234);
+        assert!(!observer.matches(&device));
+
+        let observer = Observer::new()
+            .with_product_id(0x5678)
+            .without_product_id(0x5678);
+        assert!(!observer.matches(&device));
+    }
+
+    #[test]
+    fn test_observer_serial() {
+        let device = UsbDevice {
+            serial_number: Some("ABC123".to_string()),
+            ..test_device("1", "Device")
+        };
+        let no_serial = test_device("2", "Device");
+
+        let observer = Observer::new().with_serial("ABC123");
+        assert!(observer.matches(&device));
+        assert!(!observer.matches(&no_serial));
+        assert!(!Observer::new().with_serial("ABC").matches(&device));
+
+        let observer = Observer::new().with_serial_contains("C12");
+        assert!(observer.matches(&device));
+        assert!(!observer.matches(&no_serial));
+        assert!(!Observer::new().with_serial_contains("XYZ").matches(&device));
+    }
+
+    #[test]
+    fn test_zero_poll_interval_does_not_spin() {
+        let observer = Observer::new().without_polling();
+        assert!(observer.hotplug);
+        assert_eq!(observer.effective_poll_interval(), FALLBACK_POLL_INTERVAL);
+
+        let observer = Observer::new().with_poll_interval(0);
+        assert_eq!(observer.effective_poll_interval(), FALLBACK_POLL_INTERVAL);
+
+        let observer = Observer::new().with_poll_interval(5);
+        assert_eq!(observer.effective_poll_interval(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_jittered() {
+        let interval = Duration::from_secs(1);
+        let mut rng = jitter_seed();
+
+        assert_eq!(jittered(interval, Duration::ZERO, &mut rng), interval);
+
+        let jitter = Duration::from_millis(200);
+        let waits: Vec<Duration> = (0..100)
+            .map(|_| jittered(interval, jitter, &mut rng))
+            .collect();
+        assert!(waits
+            .iter()
+            .all(|wait| *wait >= interval - jitter && *wait <= interval + jitter));
+        assert!(waits.iter().any(|wait| *wait != waits[0]));
+
+        // Jitter larger than the interval never makes the wait negative
+        let wait = jittered(interval, Duration::from_secs(10), &mut rng);
+        assert!(wait <= interval * 2);
+    }
+
+    #[test]
+    fn test_event_eq() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+
+        assert_eq!(Event::Connect(a.clone()), Event::Connect(a.clone()));
+        assert_ne!(Event::Connect(a.clone()), Event::Connect(b.clone()));
+        assert_ne!(Event::Connect(a.clone()), Event::Disconnect(a));
+        assert_eq!(Event::Initial(vec![b.clone()]), Event::Initial(vec![b]));
+    }
+
+    #[test]
+    fn test_observer_source_events() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+
+        let script = vec![vec![a.clone()], vec![a.clone(), b.clone()], vec![b.clone()]];
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_source(scripted_source(script))
+            .subscribe();
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices == [a.clone()]
+        ));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Connect(device)) if device == b
+        ));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Disconnect(device)) if device == a
+        ));
+    }
+
+    #[test]
+    fn test_observer_initial_as_connects() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+
+        let script = vec![vec![a.clone()], vec![a.clone(), b.clone()]];
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_initial_as_connects(true)
+            .with_source(scripted_source(script))
+            .subscribe();
+
+        // The initially connected device isn't reported again by the next poll
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Connect(device)) if device == a
+        ));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Connect(device)) if device == b
+        ));
+    }
+
+    #[test]
+    fn test_observer_without_initial() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+
+        let script = vec![vec![a.clone()], vec![a.clone(), b.clone()]];
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_initial(false)
+            .with_source(scripted_source(script))
+            .subscribe();
+
+        // The first event is the first change rather than the baseline
+        assert!(matches!(
+            subscription.recv_timeout(Duration::from_secs(5)),
+            Ok(Event::Connect(device)) if device == b
+        ));
+    }
+
+    #[test]
+    fn test_observer_retries_initial_scan() {
+        let a = test_device("a", "A");
+        let calls = AtomicUsize::new(0);
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_source({
+                let a = a.clone();
+                move |_, _| match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(EnumerationError::new("test", 1).into()),
+                    _ => Ok(vec![a.clone()]),
+                }
+            })
+            .subscribe();
+
+        // The failed scan isn't reported as no devices connected
+        assert!(matches!(
+            subscription.recv_timeout(Duration::from_secs(5)),
+            Ok(Event::Initial(devices)) if devices == [a]
+        ));
+    }
+
+    #[test]
+    fn test_observer_is_alive() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+
+        // An atomic rather than a mutex, which the panic would poison
+        let calls = AtomicUsize::new(0);
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_source({
+                let (a, b) = (a.clone(), b.clone());
+                move |_, _| match calls.fetch_add(1, Ordering::SeqCst) + 1 {
+                    1 => Ok(vec![a.clone()]),
+                    2 => panic!("Enumeration failed"),
+                    _ => Ok(vec![a.clone(), b.clone()]),
+                }
+            })
+            .subscribe();
+        assert!(subscription.is_alive());
+
+        // The panicking poll is skipped and the thread carries on
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices == [a.clone()]
+        ));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Connect(device)) if device == b
+        ));
+        assert!(subscription.is_alive());
+
+        let stats = subscription.stats();
+        subscription.unsubscribe().unwrap();
+        assert!(!stats.is_alive());
+    }
+
+    #[test]
+    fn test_observer_batched_events() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+        let c = test_device("c", "C");
+
+        let script = vec![vec![a.clone()], vec![b.clone(), c.clone()]];
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_batched_events(true)
+            .with_source(scripted_source(script))
+            .subscribe();
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices == [a.clone()]
+        ));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Batch { connected, disconnected, changed, reconnected })
+                if connected == [b, c] && disconnected == [a] && changed.is_empty()
+                    && reconnected.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_pending_disconnects() {
+        let a = test_device("a", "A");
+        let b = test_device("b", "B");
+        let key = |device: &UsbDevice| device.id.clone();
+        let now = Instant::now();
+        let window = Duration::from_secs(1);
+
+        let mut pending = PendingDisconnects::new(window);
+        let events = pending.debounce(
+            vec![Event::Disconnect(a.clone()), Event::Disconnect(b.clone())],
+            key,
+            now,
+        );
+        assert!(events.is_empty());
+        assert_eq!(pending.next_expiry(), Some(now + window));
+
+        let events = pending.debounce(vec![Event::Connect(a.clone())], key, now);
+        assert!(matches!(&events[..], [Event::Reconnect(device)] if *device == a));
+
+        let events = pending.debounce(vec![Event::Connect(a.clone())], key, now + window);
+        assert!(matches!(
+            &events[..],
+            [Event::Disconnect(first), Event::Connect(second)] if *first == b && *second == a
+        ));
+        assert_eq!(pending.next_expiry(), None);
+
+        let mut disabled = PendingDisconnects::new(Duration::ZERO);
+        let events = disabled.debounce(vec![Event::Disconnect(a)], key, now);
+        assert!(matches!(&events[..], [Event::Disconnect(_)]));
+    }
+
+    #[test]
+    fn test_observer_reconnect_debounce() {
+        let a = test_device("a", "A");
+
+        let script = vec![vec![a.clone()], vec![], vec![a.clone()]];
+
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_reconnect_debounce(Duration::from_secs(5))
+            .with_source(scripted_source(script))
+            .subscribe();
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices == [a.clone()]
+        ));
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Reconnect(device)) if device == a
+        ));
+    }
+
+    #[test]
+    fn test_observer_merge() {
+        let device = |id, vendor_id, base_class| UsbDevice {
+            vendor_id,
+            base_class,
+            ..test_device(id, "Device")
+        };
+        let devices = vec![
+            device("a", 0x1234, None),
+            device("hub", 0x9999, Some(DeviceBaseClass::Hub)),
+            device("both", 0x1234, Some(DeviceBaseClass::Hub)),
+            device("other", 0x9999, None),
+        ];
+
+        let subscription = Observer::new()
+            .with_vendor_id(0x1234)
+            .merge(vec![Observer::new().with_class(DeviceBaseClass::Hub)])
+            .with_source(move |_, _| Ok(devices.clone()))
+            .subscribe();
+
+        assert!(matches!(
+            subscription.recv_timeout(Duration::from_secs(5)),
+            Ok(Event::Initial(devices))
+                if devices.iter().map(|d| d.id.as_str()).eq(["a", "hub", "both"])
+        ));
+    }
+
+    #[test]
+    fn test_refresh() {
+        let devices = Arc::new(Mutex::new(Vec::new()));
+
+        let subscription = Observer::new()
+            .with_poll_interval(60)
+            .with_source({
+                let devices = devices.clone();
+                move |_, _| Ok(devices.lock().unwrap().clone())
+            })
+            .subscribe();
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Initial(devices)) if devices.is_empty()
+        ));
+
+        devices.lock().unwrap().push(test_device("a", "A"));
+        subscription.refresh();
+        assert!(matches!(
+            subscription.recv_timeout(timeout),
+            Ok(Event::Connect(device)) if device.id == "a"
+        ));
+
+        subscription.refresh();
+        subscription.unsubscribe().unwrap();
+    }
+
+    #[test]
+    fn test_subscribe_timed() {
+        let before = Instant::now();
+
+        let subscription = Observer::new()
+            .with_source(|_, _| Ok(vec![test_device("a", "A")]))
+            .subscribe_timed();
+
+        let timed = subscription.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(timed.at >= before && timed.at <= Instant::now());
+        assert!(matches!(timed.event, Event::Initial(devices) if devices.len() == 1));
+    }
+
+    #[test]
+    fn test_observer_stats() {
+        let subscription = Observer::new()
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .with_source(|_, _| Ok(vec![test_device("a", "A"), test_device("b", "B")]))
+            .subscribe();
+        let stats = subscription.stats();
+
+        subscription.recv_timeout(Duration::from_secs(5)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while stats.poll_count() < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(stats.poll_count() >= 3);
+        assert_eq!(stats.last_device_count(), 2);
+    }
+
+    #[test]
+    fn test_try_iter() {
+        let (tx_event, rx_event) = unbounded();
+        let (tx_close, _rx_close) = bounded(1);
+
+        let subscription = Subscription {
+            rx_event,
+            tx_close,
+            thread: Arc::new(Mutex::new(None)),
+            stats: Arc::default(),
+        };
+
+        tx_event
+            .send(Event::Connect(test_device("a", "A")))
+            .unwrap();
+        tx_event
+            .send(Event::Disconnect(test_device("a", "A")))
+            .unwrap();
+        assert_eq!(subscription.try_iter().count(), 2);
+        assert_eq!(subscription.try_iter().count(), 0);
+
+        drop(tx_event);
+        assert!(matches!(
+            subscription.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_next_event() {
+        let (tx_event, rx_event) = unbounded();
+        let (tx_close, _rx_close) = bounded(1);
+
+        let subscription = Subscription {
+            rx_event,
+            tx_close,
+            thread: Arc::new(Mutex::new(None)),
+            stats: Arc::default(),
+        };
+
+        let timeout = Duration::from_millis(10);
+        assert!(subscription.next_event_timeout(timeout).is_none());
+
+        tx_event
+            .send(Event::Connect(test_device("a", "A")))
+            .unwrap();
+        assert!(matches!(subscription.next_event(), Some(Event::Connect(_))));
+
+        tx_event
+            .send(Event::Disconnect(test_device("a", "A")))
+            .unwrap();
+        drop(tx_event);
+        assert!(matches!(
+            subscription.next_event_timeout(timeout),
+            Some(Event::Disconnect(_))
+        ));
+        assert!(subscription.next_event().is_none());
+    }
+
+    #[test]
+    fn test_unsubscribe_joins_thread() {
+        let subscription = Observer::new()
+            .with_hotplug(false)
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .subscribe();
+
+        assert!(subscription.unsubscribe().is_ok());
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "std-channels", not(feature = "crossbeam"))))]
+    fn test_clone_keeps_thread_alive() {
+        let subscription = Observer::new()
+            .with_hotplug(false)
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .subscribe();
+        let clone = subscription.clone();
+
+        drop(subscription);
+        thread::sleep(Duration::from_millis(100));
+
+        assert!(matches!(
+            clone.recv_timeout(Duration::from_secs(1)),
+            Ok(Event::Initial(_))
+        ));
+        assert!(!matches!(clone.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "std-channels", not(feature = "crossbeam"))))]
+    fn test_unsubscribe_clones() {
+        let subscription = Observer::new()
+            .with_hotplug(false)
+            .with_poll_interval_duration(Duration::from_millis(10))
+            .subscribe();
+        let clone = subscription.clone();
+
+        assert!(subscription.unsubscribe().is_ok());
+        assert!(clone.unsubscribe().is_ok());
+    }
+
+    #[test]
+    fn test_wait_for_device_timeout() {
+        let start = Instant::now();
+        let device = wait_for_device(0xffff, 0xffff, Duration::from_millis(500));
+
+        assert!(device.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_wait_for_disconnect_missing_device() {
+        assert!(wait_for_disconnect("missing", Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let subscription = Observer::new().subscribe();
+        let mut iter = subscription.rx_event.iter();
+
+        let initial = iter.next().expect("Should get an Event");
+        assert!(matches!(initial, Event::Initial(_)));
+
+        println!("Connect a USB device");
+
+        let connect_event = iter.next().expect("Should get an Event");
+        let connect_device = if let Event::Connect(device) = connect_event {
+            device
+        } else {
+            panic!("Expected Event::Connect. Actual: {:?}", connect_event);
+        };
+
+        println!("Disconnect that same device");
+
+        let disconnect_event = iter.next().expect("Should get an Event");
+        let disconnect_device = if let Event::Disconnect(device) = disconnect_event {
+            device
+        } else {
+            panic!("Expected Event::Disconnect. Actual: {:?}", disconnect_event);
+        };
+
+        assert_eq!(connect_device, disconnect_device);
+    }
+}
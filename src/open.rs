@@ -0,0 +1,166 @@
+use crate::UsbDevice;
+use rusb::{Device, DeviceHandle, GlobalContext, Language};
+use std::time::Duration;
+
+/// How long to wait for the device to answer each string descriptor request
+const STRING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// # String descriptors read from the device itself
+///
+/// Returned by [`UsbDevice::read_strings`]. Each field is `None` if the device
+/// doesn't have that string.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct DeviceStrings {
+    /// Live `iManufacturer` string
+    pub manufacturer: Option<String>,
+    /// Live `iProduct` string
+    pub product: Option<String>,
+    /// Live `iSerialNumber` string
+    pub serial: Option<String>,
+}
+
+impl UsbDevice {
+    /// Opens the device with [`rusb`]
+    ///
+    /// The device is found by its bus number and address where the platform
+    /// reports them, which tells identical devices apart. Otherwise, or if
+    /// nothing is at that address but the device has a serial number, it is
+    /// matched by vendor and product ID and by serial number when there is
+    /// one, opening each candidate to read its serial. Without a serial
+    /// number the first device with matching IDs is opened. Returns
+    /// `rusb::Error::NotFound` if no device matches, or the last error from
+    /// opening a candidate, such as `rusb::Error::Access`, if a candidate
+    /// couldn't be opened to check it.
+    ///
+    /// ```no_run
+    /// # let device = usb_enumeration::enumerate(Some(0x1234), None).unwrap().remove(0);
+    /// let handle = device.open_rusb()?;
+    /// # Ok::<(), rusb::Error>(())
+    /// ```
+    pub fn open_rusb(&self) -> rusb::Result<DeviceHandle<GlobalContext>> {
+        let devices = rusb::devices()?;
+
+        if let (Some(bus_number), Some(address)) = (self.bus_number, self.device_address) {
+            let found = devices.iter().find(|device| {
+                device.bus_number() == bus_number
+                    && device.address() == address
+                    && self.has_ids_of(device)
+            });
+
+            match found {
+                Some(device) => return device.open(),
+                // Another device with the same IDs can only be confirmed as
+                // this one by its serial number
+                None if self.serial_number.is_none() => return Err(rusb::Error::NotFound),
+                None => {}
+            }
+        }
+
+        let mut open_error = None;
+
+        for device in devices.iter().filter(|device| self.has_ids_of(device)) {
+            let serial_number = match &self.serial_number {
+                Some(serial_number) => serial_number,
+                None => return device.open(),
+            };
+
+            let handle = match device.open() {
+                Ok(handle) => handle,
+                Err(error) => {
+                    open_error = Some(error);
+                    continue;
+                }
+            };
+
+            let matches = device
+                .device_descriptor()
+                .and_then(|descriptor| handle.read_serial_number_string_ascii(&descriptor))
+                .is_ok_and(|serial| serial == *serial_number);
+
+            if matches {
+                return Ok(handle);
+            }
+        }
+
+        Err(open_error.unwrap_or(rusb::Error::NotFound))
+    }
+
+    /// Returns `true` if the rusb device has this device's vendor and
+    /// product ID
+    fn has_ids_of(&self, device: &Device<GlobalContext>) -> bool {
+        device.device_descriptor().is_ok_and(|descriptor| {
+            self.matches(Some(descriptor.vendor_id()), Some(descriptor.product_id()))
+        })
+    }
+
+    /// Opens the device and reads its manufacturer, product and serial number
+    /// string descriptors in the first language it supports
+    ///
+    /// The names in [`UsbDevice`] are cached by the OS, which can be stale or
+    /// translated. This asks the device instead, so it needs permission to
+    /// open it:
+    ///
+    /// - Linux: write access to the device node under `/dev/bus/usb`, usually
+    ///   granted with a udev rule
+    /// - Windows: the device must use the WinUSB driver (or another driver
+    ///   libusb supports) rather than a vendor driver
+    /// - macOS: no extra permissions, but a device held open exclusively by
+    ///   another process can't be opened
+    ///
+    /// ```no_run
+    /// # let device = usb_enumeration::enumerate(Some(0x1234), None).unwrap().remove(0);
+    /// let strings = device.read_strings()?;
+    /// println!("{:?}", strings.product);
+    /// # Ok::<(), rusb::Error>(())
+    /// ```
+    pub fn read_strings(&self) -> rusb::Result<DeviceStrings> {
+        self.read_strings_with(|languages| languages.first().copied())
+    }
+
+    /// Like [`UsbDevice::read_strings`] but in the language with the given USB
+    /// language ID, eg. `0x0409` for US English
+    ///
+    /// Returns `rusb::Error::NotFound` if the device doesn't support the
+    /// language.
+    pub fn read_strings_in(&self, language_id: u16) -> rusb::Result<DeviceStrings> {
+        self.read_strings_with(|languages| {
+            languages
+                .iter()
+                .find(|language| language.lang_id() == language_id)
+                .copied()
+        })
+    }
+
+    fn read_strings_with(
+        &self,
+        choose: impl FnOnce(&[Language]) -> Option<Language>,
+    ) -> rusb::Result<DeviceStrings> {
+        let handle = self.open_rusb()?;
+        let descriptor = handle.device().device_descriptor()?;
+
+        // Devices without any strings don't have a language table either
+        let indices = [
+            descriptor.manufacturer_string_index(),
+            descriptor.product_string_index(),
+            descriptor.serial_number_string_index(),
+        ];
+        if indices.iter().all(Option::is_none) {
+            return Ok(DeviceStrings::default());
+        }
+
+        let languages = handle.read_languages(STRING_TIMEOUT)?;
+        let language = choose(&languages).ok_or(rusb::Error::NotFound)?;
+
+        let read = |index: Option<u8>| {
+            index
+                .map(|index| handle.read_string_descriptor(language, index, STRING_TIMEOUT))
+                .transpose()
+        };
+
+        Ok(DeviceStrings {
+            manufacturer: read(indices[0])?,
+            product: read(indices[1])?,
+            serial: read(indices[2])?,
+        })
+    }
+}
@@ -0,0 +1,28 @@
+use crate::DeviceBaseClass;
+
+/// # Which devices [`enumerate_with`](crate::enumerate_with) returns
+///
+/// Every filter is off by default, so set just the ones you need:
+///
+/// ```no_run
+/// use usb_enumeration::EnumerateOptions;
+///
+/// let devices = usb_enumeration::enumerate_with(EnumerateOptions {
+///     vendor_id: Some(0x1234),
+///     ..Default::default()
+/// })?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnumerateOptions {
+    /// Optional USB Vendor ID to filter
+    pub vendor_id: Option<u16>,
+    /// Optional USB Product ID to filter
+    pub product_id: Option<u16>,
+    /// Optional base class to filter, matching the device or any of its
+    /// interfaces like [`UsbDevice::has_class`](crate::UsbDevice::has_class)
+    pub class: Option<DeviceBaseClass>,
+    /// Also return devices that aren't connected, see
+    /// [`enumerate_including_non_present`](crate::enumerate_including_non_present)
+    pub include_non_present: bool,
+}
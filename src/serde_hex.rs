@@ -0,0 +1,158 @@
+//! # Serialize Vendor and Product IDs as hex strings
+//!
+//! `UsbDevice` serializes its IDs as numbers, which are hard to recognise in
+//! config files. Use this module with `#[serde(with = "...")]` on your own
+//! fields to write them as zero-padded lowercase hex like `"046d"` instead.
+//! Deserializing accepts hex strings, with or without a `0x` prefix, and
+//! numbers.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     #[serde(with = "usb_enumeration::serde_hex")]
+//!     vendor_id: u16,
+//!     #[serde(with = "usb_enumeration::serde_hex::option")]
+//!     product_id: Option<u16>,
+//! }
+//! ```
+use serde::{de, Deserializer, Serializer};
+use std::{convert::TryFrom, fmt};
+
+/// Serializes an ID as a four digit hex string
+pub fn serialize<S: Serializer>(id: &u16, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:04x}", id))
+}
+
+/// Deserializes an ID from a hex string or a number
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    deserializer.deserialize_any(IdVisitor)
+}
+
+/// The same for `Option<u16>`, with `None` as null
+pub mod option {
+    use super::IdVisitor;
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    /// Serializes an ID as a four digit hex string, or `None` as null
+    pub fn serialize<S: Serializer>(id: &Option<u16>, serializer: S) -> Result<S::Ok, S::Error> {
+        match id {
+            Some(id) => super::serialize(id, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an ID from a hex string, a number or null
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u16>, D::Error> {
+        deserializer.deserialize_option(OptionVisitor)
+    }
+
+    struct OptionVisitor;
+
+    impl<'de> de::Visitor<'de> for OptionVisitor {
+        type Value = Option<u16>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a hex string, a number or null")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_any(IdVisitor).map(Some)
+        }
+    }
+}
+
+struct IdVisitor;
+
+impl de::Visitor<'_> for IdVisitor {
+    type Value = u16;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a hex string or a number from 0 to 0xffff")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<u16, E> {
+        let digits = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(value);
+
+        u16::from_str_radix(digits, 16)
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<u16, E> {
+        u16::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<u16, E> {
+        u16::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ids {
+        #[serde(with = "crate::serde_hex")]
+        vendor_id: u16,
+        #[serde(with = "crate::serde_hex::option")]
+        product_id: Option<u16>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ids = Ids {
+            vendor_id: 0x046d,
+            product_id: Some(0xc52b),
+        };
+        let json = serde_json::to_string(&ids).unwrap();
+        assert_eq!(json, r#"{"vendor_id":"046d","product_id":"c52b"}"#);
+        assert_eq!(serde_json::from_str::<Ids>(&json).unwrap(), ids);
+
+        let ids = Ids {
+            vendor_id: 0x0001,
+            product_id: None,
+        };
+        let json = serde_json::to_string(&ids).unwrap();
+        assert_eq!(json, r#"{"vendor_id":"0001","product_id":null}"#);
+        assert_eq!(serde_json::from_str::<Ids>(&json).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_deserialize_forms() {
+        let expected = Ids {
+            vendor_id: 0x046d,
+            product_id: Some(0xc52b),
+        };
+
+        for json in [
+            r#"{"vendor_id":1133,"product_id":50475}"#,
+            r#"{"vendor_id":"0x046D","product_id":"0xc52b"}"#,
+            r#"{"vendor_id":"46d","product_id":"C52B"}"#,
+        ] {
+            assert_eq!(serde_json::from_str::<Ids>(json).unwrap(), expected);
+        }
+
+        assert!(serde_json::from_str::<Ids>(r#"{"vendor_id":"xyz","product_id":null}"#).is_err());
+        assert!(serde_json::from_str::<Ids>(r#"{"vendor_id":65536,"product_id":null}"#).is_err());
+        assert!(serde_json::from_str::<Ids>(r#"{"vendor_id":-1,"product_id":null}"#).is_err());
+    }
+}
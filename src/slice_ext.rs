@@ -0,0 +1,69 @@
+use crate::{DeviceBaseClass, UsbDevice};
+
+/// Shortcuts for searching a list of devices, such as the result of
+/// [`enumerate`](crate::enumerate)
+///
+/// ```no_run
+/// use usb_enumeration::UsbDeviceSliceExt;
+///
+/// let devices = usb_enumeration::enumerate(None, None)?;
+///
+/// if let Some(device) = devices.find_vid_pid(0x1234, 0x5678) {
+///     println!("Found {}", device);
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub trait UsbDeviceSliceExt {
+    /// Returns the first device with this USB Vendor and Product ID
+    fn find_vid_pid(&self, vendor_id: u16, product_id: u16) -> Option<&UsbDevice>;
+
+    /// Returns `true` if any of the devices is a hub
+    fn any_hub(&self) -> bool;
+
+    /// Iterates over the devices with a base class, see
+    /// [`UsbDevice::has_class`]
+    fn of_class(&self, class: DeviceBaseClass) -> impl Iterator<Item = &UsbDevice>;
+}
+
+impl UsbDeviceSliceExt for [UsbDevice] {
+    fn find_vid_pid(&self, vendor_id: u16, product_id: u16) -> Option<&UsbDevice> {
+        self.iter()
+            .find(|device| device.matches(Some(vendor_id), Some(product_id)))
+    }
+
+    fn any_hub(&self) -> bool {
+        self.iter().any(UsbDevice::is_hub)
+    }
+
+    fn of_class(&self, class: DeviceBaseClass) -> impl Iterator<Item = &UsbDevice> {
+        self.iter().filter(move |device| device.has_class(class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_device;
+
+    #[test]
+    fn test_slice_ext() {
+        let hub = UsbDevice {
+            vendor_id: 0x1d6b,
+            product_id: 0x0002,
+            base_class: Some(DeviceBaseClass::Hub),
+            ..test_device("hub", "Hub")
+        };
+        let devices = [test_device("a", "A"), hub.clone(), test_device("b", "B")];
+
+        assert_eq!(devices.find_vid_pid(0x1234, 0x5678).unwrap().id, "a");
+        assert_eq!(devices.find_vid_pid(0x1d6b, 0x0002), Some(&hub));
+        assert_eq!(devices.find_vid_pid(0x1d6b, 0x5678), None);
+
+        assert!(devices.any_hub());
+        assert!(!devices[..1].any_hub());
+
+        let hubs: Vec<&UsbDevice> = devices.of_class(DeviceBaseClass::Hub).collect();
+        assert_eq!(hubs, [&hub]);
+        assert_eq!(devices.of_class(DeviceBaseClass::Audio).count(), 0);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::Error;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// # Statistics about an `Observer`'s background thread
+///
+/// Returned by [`Subscription::stats`](crate::Subscription::stats). The
+/// values are updated once per poll, including the initial enumeration, so
+/// they can be read at any time to check the thread is still polling and
+/// how long enumeration takes.
+#[derive(Debug, Default)]
+pub struct ObserverStats {
+    poll_count: AtomicU64,
+    last_duration_ms: AtomicU64,
+    last_device_count: AtomicUsize,
+    alive: AtomicBool,
+    last_error: Mutex<Option<Error>>,
+}
+
+impl ObserverStats {
+    /// The number of times devices have been enumerated
+    pub fn poll_count(&self) -> u64 {
+        self.poll_count.load(Ordering::Relaxed)
+    }
+
+    /// How long the last enumeration took
+    pub fn last_duration(&self) -> Duration {
+        Duration::from_millis(self.last_duration_ms.load(Ordering::Relaxed))
+    }
+
+    /// The number of devices found by the last successful enumeration
+    pub fn last_device_count(&self) -> usize {
+        self.last_device_count.load(Ordering::Relaxed)
+    }
+
+    /// Whether the background thread is still running, see
+    /// [`Subscription::is_alive`](crate::Subscription::is_alive)
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Relaxed)
+    }
+
+    /// The error from the last enumeration, or `None` if it succeeded
+    ///
+    /// A panic while enumerating is reported as [`Error::Panicked`].
+    pub fn last_error(&self) -> Option<Error> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn set_alive(&self, alive: bool) {
+        self.alive.store(alive, Ordering::Relaxed);
+    }
+
+    /// Records a poll, where `devices` is the number of devices found or the
+    /// error if enumeration failed
+    pub(crate) fn record(&self, duration: Duration, devices: Result<usize, &Error>) {
+        self.last_duration_ms
+            .store(duration.as_millis() as u64, Ordering::Relaxed);
+
+        if let Ok(devices) = devices {
+            self.last_device_count.store(devices, Ordering::Relaxed);
+        }
+
+        *self
+            .last_error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = devices.err().cloned();
+
+        self.poll_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let stats = ObserverStats::default();
+        assert_eq!(stats.poll_count(), 0);
+
+        stats.record(Duration::from_millis(12), Ok(3));
+        assert_eq!(stats.last_error(), None);
+
+        let error = Error::Panicked("bad device".to_string());
+        stats.record(Duration::from_millis(5), Err(&error));
+
+        assert_eq!(stats.poll_count(), 2);
+        assert_eq!(stats.last_duration(), Duration::from_millis(5));
+        assert_eq!(stats.last_device_count(), 3);
+        assert_eq!(stats.last_error(), Some(error));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{channel::RecvTimeoutError, observer::CLOSE_CHECK_INTERVAL, Subscription};
+use futures_channel::mpsc::unbounded;
+use futures_core::Stream;
+use std::thread;
+
+impl<E: Send + 'static> Subscription<E> {
+    /// Convert the subscription into an async `Stream` of events
+    ///
+    /// Events are forwarded from the subscription's channel by a background
+    /// thread. The stream ends when the Observer thread closes, and dropping
+    /// the stream drops the subscription.
+    ///
+    /// ```no_run
+    /// # async fn example() {
+    /// use futures::StreamExt;
+    /// use usb_enumeration::Observer;
+    ///
+    /// let mut stream = Observer::new().subscribe().into_stream();
+    ///
+    /// while let Some(event) = stream.next().await {
+    ///     println!("{:?}", event);
+    /// }
+    /// # }
+    /// ```
+    pub fn into_stream(self) -> impl Stream<Item = E> {
+        let (tx, rx) = unbounded();
+
+        thread::Builder::new()
+            .name("USB Enumeration Stream Thread".to_string())
+            .spawn(move || loop {
+                // Check periodically whether the stream has been dropped
+                match self.rx_event.recv_timeout(CLOSE_CHECK_INTERVAL) {
+                    Ok(event) => {
+                        if tx.unbounded_send(event).is_err() {
+                            return;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        if tx.is_closed() {
+                            return;
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            })
+            .expect("Could not spawn stream thread");
+
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        channel::{bounded, unbounded},
+        Event,
+    };
+    use futures::executor::block_on_stream;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_stream_ends_on_disconnect() {
+        let (tx_event, rx_event) = unbounded();
+        let (tx_close, _rx_close) = bounded(0);
+
+        let subscription = Subscription {
+            rx_event,
+            tx_close,
+            thread: Arc::new(Mutex::new(None)),
+            stats: Arc::default(),
+        };
+
+        tx_event.send(Event::Initial(Vec::new())).unwrap();
+        drop(tx_event);
+
+        let events: Vec<Event> = block_on_stream(subscription.into_stream()).collect();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], Event::Initial(_)));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{DeviceBaseClass, Error, UsbDevice};
+use std::collections::HashMap;
+
+/// A device and the devices connected to it
+#[derive(Debug, Clone)]
+pub struct UsbNode {
+    pub device: UsbDevice,
+    pub children: Vec<UsbNode>,
+}
+
+/// # Assembles devices into trees using their `parent_id`
+///
+/// Devices without a parent, or whose parent isn't in `devices`, become the
+/// top-level nodes. These are usually the root hubs. Devices keep their
+/// order within each level.
+///
+/// ```no_run
+/// let devices = usb_enumeration::enumerate(None, None)?;
+///
+/// for root in usb_enumeration::build_tree(&devices) {
+///     println!("{:?} has {} children", root.device.description, root.children.len());
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn build_tree(devices: &[UsbDevice]) -> Vec<UsbNode> {
+    let ids: HashMap<&str, usize> = devices
+        .iter()
+        .enumerate()
+        .map(|(i, device)| (device.id.as_str(), i))
+        .collect();
+
+    let mut roots = Vec::new();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); devices.len()];
+
+    for (i, device) in devices.iter().enumerate() {
+        let parent = device
+            .parent_id
+            .as_deref()
+            .and_then(|id| ids.get(id))
+            .filter(|&&parent| parent != i);
+
+        match parent {
+            Some(&parent) => children[parent].push(i),
+            None => roots.push(i),
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|i| build_node(devices, &children, i))
+        .collect()
+}
+
+/// A hub and the devices connected to its ports
+#[derive(Debug, Clone)]
+pub struct UsbHub {
+    pub device: UsbDevice,
+    /// Optional number of downstream ports from the hub descriptor's
+    /// `bNbrPorts`
+    ///
+    /// This is read from sysfs `maxchild` on Linux. Other platforms only
+    /// expose it to processes that open the hub, so it's `None` there.
+    pub port_count: Option<u8>,
+    /// The `id`s of the devices connected to the hub
+    pub downstream: Vec<String>,
+}
+
+/// # Enumerates connected USB hubs, including root hubs
+///
+/// ```no_run
+/// for hub in usb_enumeration::enumerate_hubs()? {
+///     println!("{} has {:?} ports", hub.device, hub.port_count);
+/// }
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn enumerate_hubs() -> Result<Vec<UsbHub>, Error> {
+    let devices = crate::enumerate(None, None)?;
+    Ok(hubs_from(&devices, crate::hub_port_count))
+}
+
+fn hubs_from(devices: &[UsbDevice], port_count: impl Fn(&UsbDevice) -> Option<u8>) -> Vec<UsbHub> {
+    devices
+        .iter()
+        .filter(|device| device.base_class == Some(DeviceBaseClass::Hub))
+        .map(|hub| UsbHub {
+            device: hub.clone(),
+            port_count: port_count(hub),
+            downstream: devices
+                .iter()
+                .filter(|device| device.parent_id.as_deref() == Some(hub.id.as_str()))
+                .map(|device| device.id.clone())
+                .collect(),
+        })
+        .collect()
+}
+
+fn build_node(devices: &[UsbDevice], children: &[Vec<usize>], i: usize) -> UsbNode {
+    UsbNode {
+        device: devices[i].clone(),
+        children: children[i]
+            .iter()
+            .map(|&child| build_node(devices, children, child))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_device;
+
+    fn device(id: &str, parent_id: Option<&str>) -> UsbDevice {
+        UsbDevice {
+            parent_id: parent_id.map(|id| id.to_string()),
+            ..test_device(id, "Device")
+        }
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let devices = [
+            device("keyboard", Some("hub")),
+            device("root", None),
+            device("hub", Some("root")),
+            device("mouse", Some("hub")),
+            device("orphan", Some("missing")),
+        ];
+
+        let tree = build_tree(&devices);
+        assert_eq!(tree.len(), 2);
+
+        let root = &tree[0];
+        assert_eq!(root.device.id, "root");
+        assert_eq!(root.children.len(), 1);
+
+        let hub = &root.children[0];
+        assert_eq!(hub.device.id, "hub");
+        assert!(hub
+            .children
+            .iter()
+            .map(|node| &node.device.id)
+            .eq(["keyboard", "mouse"].iter()));
+
+        assert_eq!(tree[1].device.id, "orphan");
+        assert!(tree[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_hubs_from() {
+        let hub = |id, parent_id| UsbDevice {
+            base_class: Some(DeviceBaseClass::Hub),
+            ..device(id, parent_id)
+        };
+        let devices = [
+            hub("root", None),
+            hub("hub", Some("root")),
+            device("keyboard", Some("hub")),
+            device("mouse", Some("hub")),
+        ];
+
+        let hubs = hubs_from(&devices, |hub| (hub.id == "root").then_some(4));
+        assert_eq!(hubs.len(), 2);
+        assert_eq!(hubs[0].port_count, Some(4));
+        assert_eq!(hubs[0].downstream, ["hub"]);
+        assert_eq!(hubs[1].port_count, None);
+        assert_eq!(hubs[1].downstream, ["keyboard", "mouse"]);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{common::*, Error};
+
+use std::{iter, time::Duration};
+
+/// Always fails since enumeration isn't supported on this target
+pub fn enumerate_platform(
+    _vid: Option<u16>,
+    _pid: Option<u16>,
+) -> Result<iter::Empty<Result<UsbDevice, DeviceError>>, Error> {
+    Err(Error::Unsupported)
+}
+
+/// Hubs only report their number of ports when opened
+pub fn hub_port_count(_device: &UsbDevice) -> Option<u8> {
+    None
+}
+
+/// There are no OS device notifications on this target
+pub struct HotplugMonitor;
+
+impl HotplugMonitor {
+    pub fn new() -> Option<Self> {
+        None
+    }
+
+    pub fn wait(&mut self, _timeout: Duration) -> bool {
+        false
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{common::*, Error, Event};
+
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Usb, UsbConnectionEvent};
+
+/// # Enumerates USB devices the page has been granted access to with WebUSB
+///
+/// * `vendor_id` - Optional USB Vendor ID to filter
+/// * `product_id` - Optional USB Product ID to filter
+///
+/// WebUSB only reports devices the user has already authorized, usually by
+/// picking them from the browser's `navigator.usb.requestDevice()` prompt.
+/// Other connected devices never appear. Returns [`Error::Unsupported`] if
+/// WebUSB isn't available.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), usb_enumeration::Error> {
+/// let devices = usb_enumeration::enumerate_webusb(None, None).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn enumerate_webusb(
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+) -> Result<Vec<UsbDevice>, Error> {
+    let devices = JsFuture::from(usb()?.get_devices())
+        .await
+        .map_err(|_| EnumerationError::new("navigator.usb.getDevices", 0))?;
+
+    Ok(devices
+        .iter()
+        .map(|device| usb_device_from(&device))
+        .filter(|device| device.matches(vendor_id, product_id))
+        .collect())
+}
+
+/// # Watches for WebUSB connect and disconnect events
+///
+/// `callback` is called with an `Event::Connect` or `Event::Disconnect` for
+/// each authorized device that connects or disconnects. Events stop when the
+/// returned [`WebUsbWatch`] is dropped. Returns [`Error::Unsupported`] if
+/// WebUSB isn't available.
+///
+/// Threads aren't available on wasm32 so this is used instead of an
+/// `Observer`.
+///
+/// ```no_run
+/// let watch = usb_enumeration::watch_webusb(|event| {
+///     println!("{:?}", event);
+/// })?;
+/// # Ok::<(), usb_enumeration::Error>(())
+/// ```
+pub fn watch_webusb(callback: impl Fn(Event) + 'static) -> Result<WebUsbWatch, Error> {
+    let usb = usb()?;
+    let callback = Rc::new(callback);
+
+    let on_connect = Closure::<dyn FnMut(UsbConnectionEvent)>::new({
+        let callback = callback.clone();
+        move |event: UsbConnectionEvent| callback(Event::Connect(usb_device_from(&event.device())))
+    });
+
+    let on_disconnect =
+        Closure::<dyn FnMut(UsbConnectionEvent)>::new(move |event: UsbConnectionEvent| {
+            callback(Event::Disconnect(usb_device_from(&event.device())))
+        });
+
+    usb.set_onconnect(Some(on_connect.as_ref().unchecked_ref()));
+    usb.set_ondisconnect(Some(on_disconnect.as_ref().unchecked_ref()));
+
+    Ok(WebUsbWatch {
+        usb,
+        _on_connect: on_connect,
+        _on_disconnect: on_disconnect,
+    })
+}
+
+/// Returned by [`watch_webusb`], stops the events when dropped
+pub struct WebUsbWatch {
+    usb: Usb,
+    _on_connect: Closure<dyn FnMut(UsbConnectionEvent)>,
+    _on_disconnect: Closure<dyn FnMut(UsbConnectionEvent)>,
+}
+
+impl Drop for WebUsbWatch {
+    fn drop(&mut self) {
+        self.usb.set_onconnect(None);
+        self.usb.set_ondisconnect(None);
+    }
+}
+
+/// Returns `navigator.usb`, which is undefined where WebUSB isn't available,
+/// such as in Firefox, Safari and insecure contexts
+fn usb() -> Result<Usb, Error> {
+    let window = web_sys::window().ok_or(Error::Unsupported)?;
+
+    js_sys::Reflect::get(&window.navigator(), &"usb".into())
+        .ok()
+        .and_then(|usb| usb.dyn_into::<Usb>().ok())
+        .ok_or(Error::Unsupported)
+}
+
+/// WebUSB doesn't expose a platform id so devices are identified by their IDs
+/// and serial number where they have one
+fn usb_device_from(device: &web_sys::UsbDevice) -> UsbDevice {
+    let vendor_id = device.vendor_id();
+    let product_id = device.product_id();
+    let serial_number = device.serial_number().filter(|s| !s.is_empty());
+
+    UsbDevice {
+        id: format!(
+            "{:04x}:{:04x}:{}",
+            vendor_id,
+            product_id,
+            serial_number.as_deref().unwrap_or_default()
+        ),
+        vendor_id,
+        product_id,
+        description: device.product_name().as_deref().and_then(clean_description),
+        product_name: None,
+        manufacturer: device.manufacturer_name(),
+        serial_number,
+        base_class: Some(DeviceBaseClass::from(device.device_class())),
+        sub_class: Some(device.device_subclass()),
+        protocol: Some(device.device_protocol()),
+        speed: None,
+        bus_number: None,
+        device_address: None,
+        interface_number: None,
+        device_version: Some((
+            device.device_version_major(),
+            device.device_version_minor() * 10 + device.device_version_subminor(),
+        )),
+        usb_version: Some((
+            device.usb_version_major(),
+            device.usb_version_minor() * 10 + device.usb_version_subminor(),
+        )),
+        device_path: None,
+        max_power_ma: None,
+        self_powered: None,
+        driver: None,
+        parent_id: None,
+        port_number: None,
+        num_configurations: Some(device.configurations().length() as u8),
+        interfaces: interfaces_from(device),
+        location_id: None,
+        removable: None,
+        present: true,
+        hardware_ids: Vec::new(),
+        raw_properties: None,
+        active_configuration: None,
+    }
+}
+
+/// Reads the interfaces of the active configuration, which is only set once
+/// the device has been opened
+fn interfaces_from(device: &web_sys::UsbDevice) -> Vec<UsbInterface> {
+    let configuration = match device.configuration() {
+        Some(configuration) => configuration,
+        None => return Vec::new(),
+    };
+
+    let mut interfaces: Vec<UsbInterface> = configuration
+        .interfaces()
+        .iter()
+        .map(|interface| {
+            let alternate = interface.alternate();
+            UsbInterface {
+                number: interface.interface_number(),
+                base_class: DeviceBaseClass::from(alternate.interface_class()),
+                sub_class: alternate.interface_subclass(),
+                protocol: alternate.interface_protocol(),
+                description: alternate
+                    .interface_name()
+                    .as_deref()
+                    .and_then(clean_description),
+            }
+        })
+        .collect();
+
+    interfaces.sort_by_key(|interface| interface.number);
+    interfaces
+}
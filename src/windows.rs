@@ -1,117 +1,847 @@
 use crate::common::*;
 use std::{
+    cell::Cell,
+    collections::HashMap,
     error::Error,
     ffi::OsStr,
+    fmt,
     mem::size_of,
     os::windows::ffi::OsStrExt,
     ptr::{null, null_mut},
+    time::Duration,
 };
-use winapi::um::setupapi::*;
+use winapi::{
+    shared::{
+        minwindef::{DWORD, LPARAM, LRESULT, UINT, WPARAM},
+        usbioctl::{
+            IOCTL_USB_GET_NODE_CONNECTION_INFORMATION_EX, USB_NODE_CONNECTION_INFORMATION_EX,
+            USB_PIPE_INFO,
+        },
+        usbiodef::{GUID_DEVINTERFACE_USB_DEVICE, GUID_DEVINTERFACE_USB_HUB},
+        windef::HWND,
+    },
+    um::{
+        cfgmgr32::{
+            CM_Get_Child, CM_Get_DevNode_Registry_PropertyW, CM_Get_DevNode_Status,
+            CM_Get_Device_IDW, CM_Get_Device_Interface_ListW, CM_Get_Device_Interface_List_SizeW,
+            CM_Get_Parent, CM_Get_Sibling, CM_DEVCAP_REMOVABLE, CM_DRP_COMPATIBLEIDS,
+            CM_DRP_DEVICEDESC, CM_GET_DEVICE_INTERFACE_LIST_PRESENT, CR_SUCCESS, DEVINST,
+            MAX_DEVICE_ID_LEN,
+        },
+        dbt::{
+            DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_DEVICEINTERFACE,
+            DEV_BROADCAST_DEVICEINTERFACE_W,
+        },
+        errhandlingapi::GetLastError,
+        fileapi::{CreateFileW, OPEN_EXISTING},
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        ioapiset::DeviceIoControl,
+        libloaderapi::GetModuleHandleW,
+        setupapi::*,
+        winnt::{FILE_SHARE_WRITE, GENERIC_WRITE},
+        winuser::*,
+    },
+};
+
+/// Room for the open pipes of a device when asking its hub about it
+const MAX_PIPES: usize = 30;
+
+pub fn enumerate_platform(vid: Option<u16>, pid: Option<u16>) -> Result<DeviceIter, crate::Error> {
+    device_iter(vid, pid, DIGCF_ALLCLASSES | DIGCF_PRESENT)
+}
 
-pub fn enumerate_platform(vid: Option<u16>, pid: Option<u16>) -> Vec<UsbDevice> {
-    let mut output: Vec<UsbDevice> = Vec::new();
+/// Enumerates devices including those Windows remembers from a previous
+/// connection, which have `present` set to `false`
+pub fn enumerate_non_present(
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<DeviceIter, crate::Error> {
+    device_iter(vid, pid, DIGCF_ALLCLASSES)
+}
 
+fn device_iter(
+    vid: Option<u16>,
+    pid: Option<u16>,
+    flags: DWORD,
+) -> Result<DeviceIter, crate::Error> {
     let usb: Vec<u16> = OsStr::new("USB\0").encode_wide().collect();
-    let dev_info = unsafe {
-        SetupDiGetClassDevsW(
-            null(),
-            usb.as_ptr(),
-            null_mut(),
-            DIGCF_ALLCLASSES | DIGCF_PRESENT,
+    let dev_info = unsafe { SetupDiGetClassDevsW(null(), usb.as_ptr(), null_mut(), flags) };
+
+    if dev_info == INVALID_HANDLE_VALUE {
+        return Err(
+            EnumerationError::new("SetupDiGetClassDevsW", unsafe { GetLastError() }).into(),
+        );
+    }
+
+    Ok(DeviceIter {
+        dev_info,
+        index: 0,
+        vid,
+        pid,
+    })
+}
+
+/// Lazily reads each device from a SetupAPI device information set
+pub struct DeviceIter {
+    dev_info: HDEVINFO,
+    index: DWORD,
+    vid: Option<u16>,
+    pid: Option<u16>,
+}
+
+impl Iterator for DeviceIter {
+    type Item = Result<UsbDevice, DeviceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut dev_info_data = SP_DEVINFO_DATA {
+            cbSize: size_of::<SP_DEVINFO_DATA>() as u32,
+            ..Default::default()
+        };
+
+        loop {
+            // The index advances on every iteration so skipping a filtered
+            // device can never re-fetch the same device
+            let index = self.index;
+            self.index += 1;
+
+            if unsafe { SetupDiEnumDeviceInfo(self.dev_info, index, &mut dev_info_data) } == 0 {
+                return None;
+            }
+
+            if let Some(result) =
+                usb_device_from(self.dev_info, &mut dev_info_data, index, self.vid, self.pid)
+                    .transpose()
+            {
+                return Some(result);
+            }
+        }
+    }
+}
+
+impl Drop for DeviceIter {
+    fn drop(&mut self) {
+        unsafe { SetupDiDestroyDeviceInfoList(self.dev_info) };
+    }
+}
+
+/// Reads a device, returning `None` if it doesn't match the filters
+fn usb_device_from(
+    dev_info: HDEVINFO,
+    dev_info_data: &mut SP_DEVINFO_DATA,
+    index: DWORD,
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<Option<UsbDevice>, DeviceError> {
+    let id = get_device_instance_id(dev_info, dev_info_data).ok_or_else(|| {
+        DeviceError::new(
+            format!("at index {}", index),
+            "SetupDiGetDeviceInstanceIdW",
+            "instance ID unavailable",
         )
+    })?;
+
+    let device = device_from_devinfo(
+        id,
+        |property| get_device_property(dev_info, dev_info_data, property),
+        vid,
+        pid,
+    )?;
+
+    let mut device = match device {
+        Some(device) => device,
+        None => return Ok(None),
     };
 
-    let mut dev_info_data = SP_DEVINFO_DATA {
-        cbSize: size_of::<SP_DEVINFO_DATA>() as u32,
-        ..Default::default()
+    device.parent_id = get_parent_instance_id(dev_info_data).filter(|id| id.starts_with("USB\\"));
+    device.present = is_present(dev_info_data.DevInst);
+
+    // Composite devices have a child device node for each interface
+    if device.interface_number.is_none() {
+        device.interfaces = get_interfaces(dev_info_data.DevInst);
+    }
+
+    device.raw_properties = raw_properties(dev_info, dev_info_data);
+
+    Ok(Some(device))
+}
+
+/// Builds a device from its instance ID and `SPDRP_*` registry properties,
+/// returning `None` if it doesn't match the filters
+///
+/// The fields that need the device tree are left for the caller.
+fn device_from_devinfo(
+    id: String,
+    mut property: impl FnMut(DWORD) -> Option<Vec<u8>>,
+    vid: Option<u16>,
+    pid: Option<u16>,
+) -> Result<Option<UsbDevice>, DeviceError> {
+    let hardware_ids = strings_from_multi_sz(property(SPDRP_HARDWAREID).ok_or_else(|| {
+        DeviceError::new(id.as_str(), "SPDRP_HARDWAREID", "property unavailable")
+    })?);
+
+    // The VID and PID can be in any of the hardware IDs
+    let VidPid {
+        vendor_id,
+        product_id,
+    } = hardware_ids
+        .iter()
+        .find_map(|id| parse_vid_pid(id))
+        .ok_or_else(|| {
+            DeviceError::new(
+                id.as_str(),
+                "SPDRP_HARDWAREID",
+                format!("no VID and PID in {:?}", hardware_ids),
+            )
+        })?;
+
+    let device_version = hardware_ids
+        .iter()
+        .find_map(|id| extract_revision(id).ok())
+        .map(version_from_bcd);
+
+    if let Some(vid) = vid {
+        if vid != vendor_id {
+            return Ok(None);
+        }
+    }
+
+    if let Some(pid) = pid {
+        if pid != product_id {
+            return Ok(None);
+        }
+    }
+
+    let description =
+        clean_description(&string_from_buf_u8(property(SPDRP_DEVICEDESC).ok_or_else(
+            || DeviceError::new(id.as_str(), "SPDRP_DEVICEDESC", "property unavailable"),
+        )?));
+
+    let serial_number = extract_serial_number(&id);
+    let interface_number = extract_interface_number(&id);
+
+    let manufacturer = property(SPDRP_MFG).map(string_from_buf_u8);
+
+    // Compatible IDs include the class, subclass and protocol
+    let compatible_ids = property(SPDRP_COMPATIBLEIDS)
+        .map(strings_from_multi_sz)
+        .unwrap_or_default();
+
+    let base_class = compatible_ids
+        .iter()
+        .find_map(|id| extract_base_class(id).ok());
+    let sub_class = compatible_ids
+        .iter()
+        .find_map(|id| extract_class_field(id, "&SUBCLASS_"));
+    let protocol = compatible_ids
+        .iter()
+        .find_map(|id| extract_class_field(id, "&PROT_"));
+
+    let driver = property(SPDRP_SERVICE)
+        .map(string_from_buf_u8)
+        .filter(|s| !s.is_empty());
+
+    // The address of a USB device is the port it's connected to, but
+    // interfaces don't have a port
+    let port_number = match interface_number {
+        Some(_) => None,
+        None => property(SPDRP_ADDRESS)
+            .and_then(u32_from_buf_u8)
+            .filter(|&address| address <= u8::MAX.into())
+            .map(|address| address as u8),
     };
 
-    let mut i = 0;
-    while unsafe { SetupDiEnumDeviceInfo(dev_info, i, &mut dev_info_data) } > 0 {
-        i += 1;
-        let mut buf: Vec<u8> = vec![0; 1000];
+    let removable = property(SPDRP_CAPABILITIES)
+        .and_then(u32_from_buf_u8)
+        .map(|capabilities| capabilities & CM_DEVCAP_REMOVABLE != 0);
+
+    Ok(Some(UsbDevice {
+        device_path: Some(id.clone()),
+        id,
+        vendor_id,
+        product_id,
+        description,
+        product_name: None,
+        manufacturer,
+        serial_number,
+        base_class,
+        sub_class,
+        protocol,
+        speed: None,
+        bus_number: None,
+        device_address: None,
+        interface_number,
+        device_version,
+        usb_version: None,
+        max_power_ma: None,
+        self_powered: None,
+        driver,
+        parent_id: None,
+        port_number,
+        num_configurations: None,
+        interfaces: Vec::new(),
+        location_id: None,
+        removable,
+        present: true,
+        hardware_ids,
+        raw_properties: None,
+        active_configuration: None,
+    }))
+}
+
+/// Hubs only report their number of ports when opened
+pub fn hub_port_count(_device: &UsbDevice) -> Option<u8> {
+    None
+}
+
+thread_local! {
+    // Set by the window procedure when a device arrives or is removed. The
+    // hidden window belongs to the thread that pumps its messages so no
+    // synchronisation is needed
+    static DEVICE_CHANGED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Listens for `WM_DEVICECHANGE` notifications on a hidden message-only window
+pub struct HotplugMonitor {
+    window: HWND,
+    notification: HDEVNOTIFY,
+}
+
+impl HotplugMonitor {
+    /// Returns `None` if the window or device notification could not be
+    /// created
+    pub fn new() -> Option<Self> {
+        let class_name: Vec<u16> = OsStr::new("UsbEnumerationHotplug\0")
+            .encode_wide()
+            .collect();
+
+        unsafe {
+            let instance = GetModuleHandleW(null());
+
+            let class = WNDCLASSEXW {
+                cbSize: size_of::<WNDCLASSEXW>() as u32,
+                lpfnWndProc: Some(hotplug_window_proc),
+                hInstance: instance,
+                lpszClassName: class_name.as_ptr(),
+                ..Default::default()
+            };
+
+            // This fails if the class is already registered by another
+            // Observer which is fine
+            RegisterClassExW(&class);
 
-        if unsafe {
-            SetupDiGetDeviceRegistryPropertyW(
-                dev_info,
-                &mut dev_info_data,
-                SPDRP_HARDWAREID,
+            let window = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                null(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
                 null_mut(),
-                buf.as_mut_ptr(),
-                buf.len() as u32,
+                instance,
                 null_mut(),
-            )
-        } > 0
-        {
-            if let Ok((vendor_id, product_id)) = extract_vid_pid(buf) {
-                if let Some(vid) = vid {
-                    if vid != vendor_id {
-                        continue;
-                    }
-                }
-
-                if let Some(pid) = pid {
-                    if pid != product_id {
-                        continue;
-                    }
-                }
-
-                buf = vec![0; 1000];
-
-                if unsafe {
-                    SetupDiGetDeviceRegistryPropertyW(
-                        dev_info,
-                        &mut dev_info_data,
-                        SPDRP_DEVICEDESC,
-                        null_mut(),
-                        buf.as_mut_ptr(),
-                        buf.len() as u32,
-                        null_mut(),
-                    )
-                } > 0
-                {
-                    let description = string_from_buf_u8(buf);
-
-                    let mut buf: Vec<u16> = vec![0; 1000];
-
-                    if unsafe {
-                        SetupDiGetDeviceInstanceIdW(
-                            dev_info,
-                            &mut dev_info_data,
-                            buf.as_mut_ptr(),
-                            buf.len() as u32,
-                            null_mut(),
-                        )
-                    } > 0
-                    {
-                        let id = string_from_buf_u16(buf);
-                        output.push(UsbDevice {
-                            id,
-                            vendor_id,
-                            product_id,
-                            description: Some(description),
-                        });
-                    }
-                }
+            );
+
+            if window.is_null() {
+                return None;
+            }
+
+            let mut filter = DEV_BROADCAST_DEVICEINTERFACE_W {
+                dbcc_size: size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32,
+                dbcc_devicetype: DBT_DEVTYP_DEVICEINTERFACE,
+                dbcc_classguid: GUID_DEVINTERFACE_USB_DEVICE,
+                ..Default::default()
+            };
+
+            let notification = RegisterDeviceNotificationW(
+                window as _,
+                &mut filter as *mut _ as _,
+                DEVICE_NOTIFY_WINDOW_HANDLE,
+            );
+
+            if notification.is_null() {
+                DestroyWindow(window);
+                return None;
+            }
+
+            Some(HotplugMonitor {
+                window,
+                notification,
+            })
+        }
+    }
+
+    /// Waits up to `timeout` for device notifications, returning `true` if
+    /// any were received
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        unsafe {
+            MsgWaitForMultipleObjects(0, null(), 0, timeout.as_millis() as u32, QS_ALLINPUT);
+
+            let mut msg = MSG::default();
+            while PeekMessageW(&mut msg, self.window, 0, 0, PM_REMOVE) > 0 {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
             }
         }
+
+        DEVICE_CHANGED.with(|changed| changed.replace(false))
+    }
+}
+
+impl Drop for HotplugMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            UnregisterDeviceNotification(self.notification);
+            DestroyWindow(self.window);
+        }
+    }
+}
+
+unsafe extern "system" fn hotplug_window_proc(
+    window: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_DEVICECHANGE && (wparam == DBT_DEVICEARRIVAL || wparam == DBT_DEVICEREMOVECOMPLETE)
+    {
+        DEVICE_CHANGED.with(|changed| changed.set(true));
+    }
+
+    DefWindowProcW(window, msg, wparam, lparam)
+}
+
+/// Reads a device registry property into a buffer sized to fit it
+fn get_device_property(
+    dev_info: HDEVINFO,
+    dev_info_data: &mut SP_DEVINFO_DATA,
+    property: DWORD,
+) -> Option<Vec<u8>> {
+    let mut required_size = 0;
+
+    // The first call fails with ERROR_INSUFFICIENT_BUFFER but tells us the
+    // size needed
+    unsafe {
+        SetupDiGetDeviceRegistryPropertyW(
+            dev_info,
+            dev_info_data,
+            property,
+            null_mut(),
+            null_mut(),
+            0,
+            &mut required_size,
+        )
+    };
+
+    if required_size == 0 {
+        return None;
+    }
+
+    let mut buf: Vec<u8> = vec![0; required_size as usize];
+
+    if unsafe {
+        SetupDiGetDeviceRegistryPropertyW(
+            dev_info,
+            dev_info_data,
+            property,
+            null_mut(),
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+            null_mut(),
+        )
+    } > 0
+    {
+        Some(buf)
+    } else {
+        None
+    }
+}
+
+/// Reads the device instance ID with a buffer sized to fit it
+fn get_device_instance_id(
+    dev_info: HDEVINFO,
+    dev_info_data: &mut SP_DEVINFO_DATA,
+) -> Option<String> {
+    let mut required_size = 0;
+
+    unsafe {
+        SetupDiGetDeviceInstanceIdW(dev_info, dev_info_data, null_mut(), 0, &mut required_size)
+    };
+
+    if required_size == 0 {
+        return None;
+    }
+
+    let mut buf: Vec<u16> = vec![0; required_size as usize];
+
+    if unsafe {
+        SetupDiGetDeviceInstanceIdW(
+            dev_info,
+            dev_info_data,
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+            null_mut(),
+        )
+    } > 0
+    {
+        Some(string_from_buf_u16(buf))
+    } else {
+        None
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ParseError;
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Parse Error")?;
+        Ok(())
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+fn extract_revision(id: &str) -> Result<u16, Box<dyn Error + Send + Sync>> {
+    // Hardware IDs include the bcdDevice like USB\VID_046D&PID_C52B&REV_1203
+    let id = id.to_uppercase();
+
+    let rev = id.find("REV_").ok_or(ParseError)?;
+    Ok(u16::from_str_radix(
+        id.get(rev + 4..rev + 8).ok_or(ParseError)?,
+        16,
+    )?)
+}
+
+fn extract_base_class(id: &str) -> Result<DeviceBaseClass, Box<dyn Error + Send + Sync>> {
+    // Compatible IDs are in the form USB\Class_09&SubClass_00&Prot_00
+    let id = id.to_uppercase();
+
+    let class = id.find("\\CLASS_").ok_or(ParseError)?;
+    let code = u8::from_str_radix(id.get(class + 7..class + 9).ok_or(ParseError)?, 16)?;
+
+    Ok(DeviceBaseClass::from(code))
+}
+
+/// Parses a two digit hex field like `&SUBCLASS_` from a compatible ID
+fn extract_class_field(id: &str, field: &str) -> Option<u8> {
+    let id = id.to_uppercase();
+
+    let start = id.find(field)? + field.len();
+    u8::from_str_radix(id.get(start..start + 2)?, 16).ok()
+}
+
+fn extract_interface_number(id: &str) -> Option<u8> {
+    // Composite device interfaces have IDs like USB\VID_046D&PID_C52B&MI_00
+    let id = id.to_uppercase();
+
+    let mi = id.find("&MI_")?;
+    u8::from_str_radix(id.get(mi + 4..mi + 6)?, 16).ok()
+}
+
+fn extract_serial_number(id: &str) -> Option<String> {
+    // Composite device interfaces don't have their own serial number
+    if id.to_uppercase().contains("&MI_") {
+        return None;
+    }
+
+    // Windows generates an instance ID containing '&' when the device has no
+    // serial number
+    let serial = id.rsplit('\\').next()?;
+    if serial.is_empty() || serial.contains('&') {
+        return None;
+    }
+
+    Some(serial.to_string())
+}
+
+/// Reads the instance ID of the device's parent in the device tree
+fn get_parent_instance_id(dev_info_data: &SP_DEVINFO_DATA) -> Option<String> {
+    let mut parent: DEVINST = 0;
+
+    if unsafe { CM_Get_Parent(&mut parent, dev_info_data.DevInst, 0) } != CR_SUCCESS {
+        return None;
+    }
+
+    get_devinst_id(parent)
+}
+
+/// A `SPDRP_*` code, its name and how to turn its buffer into a string
+type RawProperty<'a> = (DWORD, &'a str, &'a dyn Fn(Vec<u8>) -> String);
+
+/// Reads the device registry properties when the `raw-properties` feature is
+/// enabled
+fn raw_properties(
+    dev_info: HDEVINFO,
+    dev_info_data: &mut SP_DEVINFO_DATA,
+) -> Option<HashMap<String, String>> {
+    if !cfg!(feature = "raw-properties") {
+        return None;
+    }
+
+    let multi_sz = |buf| strings_from_multi_sz(buf).join(",");
+    let number = |buf| {
+        u32_from_buf_u8(buf)
+            .map(|n| n.to_string())
+            .unwrap_or_default()
+    };
+
+    let properties: [RawProperty; 16] = [
+        (SPDRP_DEVICEDESC, "SPDRP_DEVICEDESC", &string_from_buf_u8),
+        (SPDRP_HARDWAREID, "SPDRP_HARDWAREID", &multi_sz),
+        (SPDRP_COMPATIBLEIDS, "SPDRP_COMPATIBLEIDS", &multi_sz),
+        (SPDRP_SERVICE, "SPDRP_SERVICE", &string_from_buf_u8),
+        (SPDRP_CLASS, "SPDRP_CLASS", &string_from_buf_u8),
+        (SPDRP_CLASSGUID, "SPDRP_CLASSGUID", &string_from_buf_u8),
+        (SPDRP_DRIVER, "SPDRP_DRIVER", &string_from_buf_u8),
+        (SPDRP_MFG, "SPDRP_MFG", &string_from_buf_u8),
+        (
+            SPDRP_FRIENDLYNAME,
+            "SPDRP_FRIENDLYNAME",
+            &string_from_buf_u8,
+        ),
+        (
+            SPDRP_LOCATION_INFORMATION,
+            "SPDRP_LOCATION_INFORMATION",
+            &string_from_buf_u8,
+        ),
+        (
+            SPDRP_PHYSICAL_DEVICE_OBJECT_NAME,
+            "SPDRP_PHYSICAL_DEVICE_OBJECT_NAME",
+            &string_from_buf_u8,
+        ),
+        (SPDRP_CAPABILITIES, "SPDRP_CAPABILITIES", &number),
+        (SPDRP_ADDRESS, "SPDRP_ADDRESS", &number),
+        (SPDRP_BUSNUMBER, "SPDRP_BUSNUMBER", &number),
+        (SPDRP_LOCATION_PATHS, "SPDRP_LOCATION_PATHS", &multi_sz),
+        (
+            SPDRP_ENUMERATOR_NAME,
+            "SPDRP_ENUMERATOR_NAME",
+            &string_from_buf_u8,
+        ),
+    ];
+
+    Some(
+        properties
+            .iter()
+            .filter_map(|(property, name, to_string)| {
+                let buf = get_device_property(dev_info, dev_info_data, *property)?;
+                Some((name.to_string(), to_string(buf)))
+            })
+            .collect(),
+    )
+}
+
+/// Asks the hub a device is connected to for its active configuration, see
+/// [`UsbDevice::read_active_configuration`]
+pub(crate) fn read_active_configuration(device: &UsbDevice) -> Option<u8> {
+    // Only the hub knows the configuration of the device on each port
+    match (&device.parent_id, device.port_number) {
+        (Some(parent_id), Some(port_number)) if device.present => {
+            get_active_configuration(parent_id, port_number)
+        }
+        _ => None,
+    }
+}
+
+/// Asks the hub for the active configuration of the device on `port_number`
+fn get_active_configuration(hub_id: &str, port_number: u8) -> Option<u8> {
+    let path = get_hub_interface_path(hub_id)?;
+
+    let hub = unsafe {
+        CreateFileW(
+            path.as_ptr(),
+            GENERIC_WRITE,
+            FILE_SHARE_WRITE,
+            null_mut(),
+            OPEN_EXISTING,
+            0,
+            null_mut(),
+        )
+    };
+
+    if hub == INVALID_HANDLE_VALUE {
+        return None;
     }
 
-    unsafe { SetupDiDestroyDeviceInfoList(dev_info) };
+    // Leave room for the open pipes, which follow the connection information
+    let mut buf: Vec<u8> = vec![
+        0;
+        size_of::<USB_NODE_CONNECTION_INFORMATION_EX>()
+            + MAX_PIPES * size_of::<USB_PIPE_INFO>()
+    ];
+    let info = buf.as_mut_ptr() as *mut USB_NODE_CONNECTION_INFORMATION_EX;
+    unsafe { (*info).ConnectionIndex = port_number.into() };
+
+    let mut returned = 0;
+    let result = unsafe {
+        DeviceIoControl(
+            hub,
+            IOCTL_USB_GET_NODE_CONNECTION_INFORMATION_EX,
+            info as _,
+            buf.len() as u32,
+            info as _,
+            buf.len() as u32,
+            &mut returned,
+            null_mut(),
+        )
+    };
+
+    unsafe { CloseHandle(hub) };
 
-    output
+    if result == 0 {
+        return None;
+    }
+
+    Some(unsafe { (*info).CurrentConfigurationValue })
 }
 
-fn extract_vid_pid(buf: Vec<u8>) -> Result<(u16, u16), Box<dyn Error + Send + Sync>> {
-    let id = string_from_buf_u8(buf).to_uppercase();
+/// Finds the path of the hub interface of a hub device node, which is what
+/// the hub IOCTLs are sent to
+fn get_hub_interface_path(hub_id: &str) -> Option<Vec<u16>> {
+    let mut guid = GUID_DEVINTERFACE_USB_HUB;
+    let mut hub_id: Vec<u16> = OsStr::new(hub_id).encode_wide().chain(Some(0)).collect();
+    let mut len = 0;
 
-    let vid = id.find("VID_").ok_or(ParseError)?;
-    let pid = id.find("PID_").ok_or(ParseError)?;
+    if unsafe {
+        CM_Get_Device_Interface_List_SizeW(
+            &mut len,
+            &mut guid,
+            hub_id.as_mut_ptr(),
+            CM_GET_DEVICE_INTERFACE_LIST_PRESENT,
+        )
+    } != CR_SUCCESS
+    {
+        return None;
+    }
 
-    Ok((
-        u16::from_str_radix(&id[vid + 4..vid + 8], 16)?,
-        u16::from_str_radix(&id[pid + 4..pid + 8], 16)?,
-    ))
+    let mut buf: Vec<u16> = vec![0; len as usize];
+
+    if unsafe {
+        CM_Get_Device_Interface_ListW(
+            &mut guid,
+            hub_id.as_mut_ptr(),
+            buf.as_mut_ptr(),
+            len,
+            CM_GET_DEVICE_INTERFACE_LIST_PRESENT,
+        )
+    } != CR_SUCCESS
+    {
+        return None;
+    }
+
+    // The list is double null terminated, so keep the first path and its
+    // terminator
+    let end = buf.iter().position(|&c| c == 0).filter(|&end| end > 0)?;
+    buf.truncate(end + 1);
+    Some(buf)
+}
+
+/// Only connected devices have a device node with a status
+fn is_present(devinst: DEVINST) -> bool {
+    let mut status = 0;
+    let mut problem = 0;
+
+    unsafe { CM_Get_DevNode_Status(&mut status, &mut problem, devinst, 0) == CR_SUCCESS }
+}
+
+/// Reads the instance ID of a device node
+fn get_devinst_id(devinst: DEVINST) -> Option<String> {
+    let mut buf: Vec<u16> = vec![0; MAX_DEVICE_ID_LEN];
+
+    if unsafe { CM_Get_Device_IDW(devinst, buf.as_mut_ptr(), buf.len() as u32, 0) } != CR_SUCCESS {
+        return None;
+    }
+
+    Some(string_from_buf_u16(buf))
+}
+
+/// Reads a device node registry property into a buffer sized to fit it
+fn get_devinst_property(devinst: DEVINST, property: DWORD) -> Option<Vec<u8>> {
+    let mut required_size = 0;
+
+    // The first call fails with CR_BUFFER_SMALL but tells us the size needed
+    unsafe {
+        CM_Get_DevNode_Registry_PropertyW(
+            devinst,
+            property,
+            null_mut(),
+            null_mut(),
+            &mut required_size,
+            0,
+        )
+    };
+
+    if required_size == 0 {
+        return None;
+    }
+
+    let mut buf: Vec<u8> = vec![0; required_size as usize];
+
+    if unsafe {
+        CM_Get_DevNode_Registry_PropertyW(
+            devinst,
+            property,
+            null_mut(),
+            buf.as_mut_ptr() as _,
+            &mut required_size,
+            0,
+        )
+    } == CR_SUCCESS
+    {
+        Some(buf)
+    } else {
+        None
+    }
+}
+
+/// Reads the interfaces of a composite device from its child device nodes,
+/// which have instance IDs like `USB\VID_046D&PID_C52B&MI_00\...`
+fn get_interfaces(devinst: DEVINST) -> Vec<UsbInterface> {
+    let mut interfaces = Vec::new();
+    let mut child: DEVINST = 0;
+    let mut result = unsafe { CM_Get_Child(&mut child, devinst, 0) };
+
+    while result == CR_SUCCESS {
+        if let Some(interface) = get_interface(child) {
+            interfaces.push(interface);
+        }
+
+        let current = child;
+        result = unsafe { CM_Get_Sibling(&mut child, current, 0) };
+    }
+
+    interfaces.sort_by_key(|interface| interface.number);
+    interfaces
+}
+
+fn get_interface(devinst: DEVINST) -> Option<UsbInterface> {
+    let number = extract_interface_number(&get_devinst_id(devinst)?)?;
+
+    let compatible_ids = get_devinst_property(devinst, CM_DRP_COMPATIBLEIDS)
+        .map(strings_from_multi_sz)
+        .unwrap_or_default();
+
+    Some(UsbInterface {
+        number,
+        base_class: compatible_ids
+            .iter()
+            .find_map(|id| extract_base_class(id).ok())?,
+        sub_class: compatible_ids
+            .iter()
+            .find_map(|id| extract_class_field(id, "&SUBCLASS_"))?,
+        protocol: compatible_ids
+            .iter()
+            .find_map(|id| extract_class_field(id, "&PROT_"))?,
+        description: get_devinst_property(devinst, CM_DRP_DEVICEDESC)
+            .and_then(|buf| clean_description(&string_from_buf_u8(buf))),
+    })
 }
 
 fn string_from_buf_u16(buf: Vec<u16>) -> String {
@@ -124,12 +854,186 @@ fn string_from_buf_u16(buf: Vec<u16>) -> String {
     out
 }
 
-fn string_from_buf_u8(buf: Vec<u8>) -> String {
-    let str_vec: Vec<u16> = buf
-        .chunks_exact(2)
-        .into_iter()
+fn u16_from_buf_u8(buf: Vec<u8>) -> Vec<u16> {
+    buf.chunks_exact(2)
         .map(|a| u16::from_ne_bytes([a[0], a[1]]))
-        .collect();
+        .collect()
+}
 
-    string_from_buf_u16(str_vec)
+fn u32_from_buf_u8(buf: Vec<u8>) -> Option<u32> {
+    match buf[..] {
+        [a, b, c, d, ..] => Some(u32::from_ne_bytes([a, b, c, d])),
+        _ => None,
+    }
+}
+
+fn string_from_buf_u8(buf: Vec<u8>) -> String {
+    string_from_buf_u16(u16_from_buf_u8(buf))
+}
+
+/// Splits a `REG_MULTI_SZ` buffer into its strings
+fn strings_from_multi_sz(buf: Vec<u8>) -> Vec<String> {
+    u16_from_buf_u8(buf)
+        .split(|c| *c == 0)
+        .take_while(|s| !s.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_serial_number() {
+        assert_eq!(
+            extract_serial_number("USB\\VID_0CE9&PID_1220\\0000000004BE"),
+            Some("0000000004BE".to_string())
+        );
+        assert_eq!(
+            extract_serial_number("USB\\VID_046D&PID_C52B\\5&17411534&0&11"),
+            None
+        );
+        assert_eq!(
+            extract_serial_number("USB\\VID_046D&PID_C52B&MI_00\\6&12D311A2&0&0000"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_interface_number() {
+        assert_eq!(
+            extract_interface_number("USB\\VID_046D&PID_C52B&MI_00\\6&12D311A2&0&0000"),
+            Some(0)
+        );
+        assert_eq!(
+            extract_interface_number("USB\\VID_046D&PID_C52B&MI_0A\\6&12D311A2&0&000A"),
+            Some(10)
+        );
+        assert_eq!(
+            extract_interface_number("USB\\VID_046D&PID_C52B\\5&17411534&0&11"),
+            None
+        );
+    }
+
+    fn buf_from_str(s: &str) -> Vec<u8> {
+        s.encode_utf16()
+            .flat_map(|c| c.to_ne_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_base_class() {
+        assert_eq!(
+            extract_base_class("USB\\Class_09&SubClass_00&Prot_00").ok(),
+            Some(DeviceBaseClass::Hub)
+        );
+    }
+
+    #[test]
+    fn test_extract_revision() {
+        assert_eq!(
+            extract_revision("USB\\VID_046D&PID_C52B&REV_1201").ok(),
+            Some(0x1201)
+        );
+        assert!(extract_revision("USB\\VID_046D&PID_C52B").is_err());
+    }
+
+    #[test]
+    fn test_extract_class_field() {
+        let id = "USB\\Class_07&SubClass_01&Prot_04";
+        assert_eq!(extract_class_field(id, "&SUBCLASS_"), Some(0x01));
+        assert_eq!(extract_class_field(id, "&PROT_"), Some(0x04));
+        assert_eq!(extract_class_field("USB\\Class_07", "&PROT_"), None);
+    }
+
+    #[test]
+    fn test_description_from_buf() {
+        let buf = buf_from_str("USB Composite Device\u{1}   \0garbage");
+        assert_eq!(
+            clean_description(&string_from_buf_u8(buf)).as_deref(),
+            Some("USB Composite Device")
+        );
+        assert_eq!(
+            clean_description(&string_from_buf_u8(buf_from_str("USB\tReceiver\0"))).as_deref(),
+            Some("USB Receiver")
+        );
+        assert_eq!(
+            clean_description(&string_from_buf_u8(buf_from_str("  \0"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_device_from_devinfo() {
+        let properties = |property| match property {
+            SPDRP_HARDWAREID => Some(buf_from_str(
+                "USB\\VID_0CE9&PID_1220&REV_0214\0USB\\VID_0CE9&PID_1220\0\0",
+            )),
+            SPDRP_DEVICEDESC => Some(buf_from_str("PicoScope 4000 series\0")),
+            SPDRP_COMPATIBLEIDS => Some(buf_from_str("USB\\Class_42&SubClass_01&Prot_02\0\0")),
+            SPDRP_ADDRESS => Some(3u32.to_ne_bytes().to_vec()),
+            _ => None,
+        };
+
+        let device = device_from_devinfo(
+            "USB\\VID_0CE9&PID_1220\\0000000004BE".to_string(),
+            properties,
+            None,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(device.vendor_id, 0x0ce9);
+        assert_eq!(device.product_id, 0x1220);
+        assert_eq!(device.device_version, Some((2, 14)));
+        assert_eq!(device.description.as_deref(), Some("PicoScope 4000 series"));
+        assert_eq!(device.serial_number.as_deref(), Some("0000000004BE"));
+        assert_eq!(device.base_class, Some(DeviceBaseClass::Unknown(0x42)));
+        assert_eq!(device.sub_class, Some(0x01));
+        assert_eq!(device.protocol, Some(0x02));
+        assert_eq!(device.port_number, Some(3));
+        assert_eq!(device.manufacturer, None);
+
+        // Devices without a serial number have a generated instance ID
+        let device = device_from_devinfo(
+            "USB\\VID_0CE9&PID_1220\\5&17411534&0&11".to_string(),
+            properties,
+            None,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(device.serial_number, None);
+
+        let filtered = device_from_devinfo(String::new(), properties, Some(0x1234), None);
+        assert_eq!(filtered, Ok(None));
+
+        let missing = device_from_devinfo("ROOT\\HUB".to_string(), |_| None, None, None);
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_strings_from_multi_sz() {
+        let buf = buf_from_str("USB\\VID_046D&PID_C52B&REV_1201\0USB\\VID_046D&PID_C52B\0\0");
+
+        assert_eq!(
+            strings_from_multi_sz(buf),
+            vec![
+                "USB\\VID_046D&PID_C52B&REV_1201".to_string(),
+                "USB\\VID_046D&PID_C52B".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_vid_pid_any_hardware_id() {
+        let buf = buf_from_str("ROOT\\DEVICE\0USB\\VID_046D&PID_C52B\0\0");
+        let ids = strings_from_multi_sz(buf)
+            .iter()
+            .find_map(|id| parse_vid_pid(id));
+
+        assert_eq!(ids, Some(VidPid::new(0x046d, 0xc52b)));
+    }
 }